@@ -2,16 +2,64 @@
 #include <stdint.h>
 #include <stdbool.h>
 bool __swift_bridge__$init_storage(void* db_path, void* encryption_key);
+void* __swift_bridge__$init_storage_result(void* db_path, void* encryption_key);
+void* __swift_bridge__$init_storage_with_config(void* db_path, void* config_json);
 bool __swift_bridge__$migrate_database(void* plain_path, void* encrypted_path, void* encryption_key);
-bool __swift_bridge__$save_clipboard_entry(void* content_type, void* text, void* source_app);
-bool __swift_bridge__$save_clipboard_image(struct __private__FfiSlice image_data, void* source_app);
+void* __swift_bridge__$migrate_database_result(void* plain_path, void* encrypted_path, void* encryption_key);
+bool __swift_bridge__$rekey_database(void* old_key, void* new_key);
+void* __swift_bridge__$rekey_database_result(void* old_key, void* new_key);
+void* __swift_bridge__$rekey_database_with_keys(void* old_key_json, void* new_key_json);
+void* __swift_bridge__$backup_database(void* path, void* backup_key_json);
+void* __swift_bridge__$restore_database(void* path, void* backup_key_json);
+void* __swift_bridge__$get_backup_progress(void);
+void* __swift_bridge__$export_history(void* path, void* image_dir);
+void* __swift_bridge__$import_history(void* path, void* image_dir);
+void* __swift_bridge__$import_from_app(void* source, void* path);
+void* __swift_bridge__$start_server(void* socket_path, void* token_path);
+void* __swift_bridge__$stop_server(void);
+bool __swift_bridge__$save_clipboard_entry(void* content_type, void* text, void* source_app, void* bundle_id);
+void* __swift_bridge__$save_clipboard_entry_result(void* content_type, void* text, void* source_app, void* bundle_id);
+void* __swift_bridge__$save_clipboard_entry_with_ttl(void* content_type, void* text, void* source_app, void* bundle_id, uint32_t ttl_seconds);
+bool __swift_bridge__$save_clipboard_image(struct __private__FfiSlice image_data, void* source_app, void* bundle_id);
+void* __swift_bridge__$save_clipboard_image_result(struct __private__FfiSlice image_data, void* source_app, void* bundle_id);
 void* __swift_bridge__$get_recent_entries(int32_t limit);
 bool __swift_bridge__$delete_entry(int64_t id);
+void* __swift_bridge__$delete_entry_result(int64_t id);
 void* __swift_bridge__$get_entry_text(int64_t id);
 void* __swift_bridge__$get_entry_image(int64_t id);
+void* __swift_bridge__$get_entry_thumbnail(int64_t id, uint32_t max_px);
 void* __swift_bridge__$search_entries(void* query, int32_t limit);
+void* __swift_bridge__$search_entries_with_snippets(void* query, int32_t limit);
+void* __swift_bridge__$search_entries_ranked(void* query, int32_t limit, void* sort_order);
+void* __swift_bridge__$search_entries_fuzzy(void* query, int32_t limit, int32_t min_hits, int32_t scan_budget);
 void* __swift_bridge__$get_entries_before(int64_t before_timestamp, int32_t limit);
 bool __swift_bridge__$touch_entry(int64_t id);
+void* __swift_bridge__$touch_entry_result(int64_t id);
 int64_t __swift_bridge__$cleanup_old_entries(int32_t max_age_days);
+void* __swift_bridge__$cleanup_old_entries_result(int32_t max_age_days);
+void* __swift_bridge__$set_entry_expiry(int64_t id, int64_t expires_at);
+int64_t __swift_bridge__$purge_expired(void);
+void* __swift_bridge__$purge_expired_result(void);
+void* __swift_bridge__$apply_retention(void* policy_json);
+void* __swift_bridge__$pin_entry(int64_t id);
+void* __swift_bridge__$unpin_entry(int64_t id);
+void* __swift_bridge__$get_pinned_entries(void);
+void* __swift_bridge__$reorder_pins(void* ids_json);
+void* __swift_bridge__$create_tag(void* name);
+void* __swift_bridge__$rename_tag(int64_t id, void* new_name);
+void* __swift_bridge__$delete_tag(int64_t id);
+void* __swift_bridge__$list_tags(void);
+void* __swift_bridge__$tag_entry(int64_t entry_id, int64_t tag_id);
+void* __swift_bridge__$untag_entry(int64_t entry_id, int64_t tag_id);
+void* __swift_bridge__$get_entries_by_tag(int64_t tag_id, void* cursor, void* direction, int32_t limit);
+void* __swift_bridge__$list_entries(void* filter_json, void* cursor, int32_t limit);
+void* __swift_bridge__$get_entries_page(void* filter_json, void* cursor, void* direction, int32_t limit);
+void* __swift_bridge__$search_entries_page(void* query, void* cursor, void* direction, int32_t limit);
+void* __swift_bridge__$get_sensitive_rules(void);
+void* __swift_bridge__$set_sensitive_rules(void* rules_json);
+void* __swift_bridge__$add_ignored_app(void* kind, void* pattern);
+void* __swift_bridge__$remove_ignored_app(int64_t id);
+void* __swift_bridge__$list_ignored_apps(void);
+void* __swift_bridge__$purge_ignored_app_entries(int64_t id);
 
 
@@ -1,14 +1,65 @@
 public func init_storage<GenericIntoRustString: IntoRustString>(_ db_path: GenericIntoRustString, _ encryption_key: GenericIntoRustString) -> Bool {
     __swift_bridge__$init_storage({ let rustString = db_path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = encryption_key.intoRustString(); rustString.isOwned = false; return rustString.ptr }())
 }
+public func init_storage_result<GenericIntoRustString: IntoRustString>(_ db_path: GenericIntoRustString, _ encryption_key: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$init_storage_result({ let rustString = db_path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = encryption_key.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func init_storage_with_config<GenericIntoRustString: IntoRustString>(_ db_path: GenericIntoRustString, _ config_json: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$init_storage_with_config({ let rustString = db_path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = config_json.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
 public func migrate_database<GenericIntoRustString: IntoRustString>(_ plain_path: GenericIntoRustString, _ encrypted_path: GenericIntoRustString, _ encryption_key: GenericIntoRustString) -> Bool {
     __swift_bridge__$migrate_database({ let rustString = plain_path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = encrypted_path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = encryption_key.intoRustString(); rustString.isOwned = false; return rustString.ptr }())
 }
-public func save_clipboard_entry<GenericIntoRustString: IntoRustString>(_ content_type: GenericIntoRustString, _ text: GenericIntoRustString, _ source_app: GenericIntoRustString) -> Bool {
-    __swift_bridge__$save_clipboard_entry({ let rustString = content_type.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = text.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = source_app.intoRustString(); rustString.isOwned = false; return rustString.ptr }())
+public func migrate_database_result<GenericIntoRustString: IntoRustString>(_ plain_path: GenericIntoRustString, _ encrypted_path: GenericIntoRustString, _ encryption_key: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$migrate_database_result({ let rustString = plain_path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = encrypted_path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = encryption_key.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func rekey_database<GenericIntoRustString: IntoRustString>(_ old_key: GenericIntoRustString, _ new_key: GenericIntoRustString) -> Bool {
+    __swift_bridge__$rekey_database({ let rustString = old_key.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = new_key.intoRustString(); rustString.isOwned = false; return rustString.ptr }())
+}
+public func rekey_database_result<GenericIntoRustString: IntoRustString>(_ old_key: GenericIntoRustString, _ new_key: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$rekey_database_result({ let rustString = old_key.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = new_key.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func rekey_database_with_keys<GenericIntoRustString: IntoRustString>(_ old_key_json: GenericIntoRustString, _ new_key_json: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$rekey_database_with_keys({ let rustString = old_key_json.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = new_key_json.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func backup_database<GenericIntoRustString: IntoRustString>(_ path: GenericIntoRustString, _ backup_key_json: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$backup_database({ let rustString = path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = backup_key_json.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func restore_database<GenericIntoRustString: IntoRustString>(_ path: GenericIntoRustString, _ backup_key_json: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$restore_database({ let rustString = path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = backup_key_json.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func get_backup_progress() -> RustString {
+    RustString(ptr: __swift_bridge__$get_backup_progress())
+}
+public func export_history<GenericIntoRustString: IntoRustString>(_ path: GenericIntoRustString, _ image_dir: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$export_history({ let rustString = path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = image_dir.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func import_history<GenericIntoRustString: IntoRustString>(_ path: GenericIntoRustString, _ image_dir: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$import_history({ let rustString = path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = image_dir.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func import_from_app<GenericIntoRustString: IntoRustString>(_ source: GenericIntoRustString, _ path: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$import_from_app({ let rustString = source.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = path.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func start_server<GenericIntoRustString: IntoRustString>(_ socket_path: GenericIntoRustString, _ token_path: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$start_server({ let rustString = socket_path.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = token_path.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
 }
-public func save_clipboard_image<GenericIntoRustString: IntoRustString>(_ image_data: UnsafeBufferPointer<UInt8>, _ source_app: GenericIntoRustString) -> Bool {
-    __swift_bridge__$save_clipboard_image(image_data.toFfiSlice(), { let rustString = source_app.intoRustString(); rustString.isOwned = false; return rustString.ptr }())
+public func stop_server() -> RustString {
+    RustString(ptr: __swift_bridge__$stop_server())
+}
+public func save_clipboard_entry<GenericIntoRustString: IntoRustString>(_ content_type: GenericIntoRustString, _ text: GenericIntoRustString, _ source_app: GenericIntoRustString, _ bundle_id: GenericIntoRustString) -> Bool {
+    __swift_bridge__$save_clipboard_entry({ let rustString = content_type.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = text.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = source_app.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = bundle_id.intoRustString(); rustString.isOwned = false; return rustString.ptr }())
+}
+public func save_clipboard_entry_result<GenericIntoRustString: IntoRustString>(_ content_type: GenericIntoRustString, _ text: GenericIntoRustString, _ source_app: GenericIntoRustString, _ bundle_id: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$save_clipboard_entry_result({ let rustString = content_type.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = text.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = source_app.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = bundle_id.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func save_clipboard_entry_with_ttl<GenericIntoRustString: IntoRustString>(_ content_type: GenericIntoRustString, _ text: GenericIntoRustString, _ source_app: GenericIntoRustString, _ bundle_id: GenericIntoRustString, _ ttl_seconds: UInt32) -> RustString {
+    RustString(ptr: __swift_bridge__$save_clipboard_entry_with_ttl({ let rustString = content_type.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = text.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = source_app.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = bundle_id.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), ttl_seconds))
+}
+public func save_clipboard_image<GenericIntoRustString: IntoRustString>(_ image_data: UnsafeBufferPointer<UInt8>, _ source_app: GenericIntoRustString, _ bundle_id: GenericIntoRustString) -> Bool {
+    __swift_bridge__$save_clipboard_image(image_data.toFfiSlice(), { let rustString = source_app.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = bundle_id.intoRustString(); rustString.isOwned = false; return rustString.ptr }())
+}
+public func save_clipboard_image_result<GenericIntoRustString: IntoRustString>(_ image_data: UnsafeBufferPointer<UInt8>, _ source_app: GenericIntoRustString, _ bundle_id: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$save_clipboard_image_result(image_data.toFfiSlice(), { let rustString = source_app.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = bundle_id.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
 }
 public func get_recent_entries(_ limit: Int32) -> RustString {
     RustString(ptr: __swift_bridge__$get_recent_entries(limit))
@@ -16,23 +67,116 @@ public func get_recent_entries(_ limit: Int32) -> RustString {
 public func delete_entry(_ id: Int64) -> Bool {
     __swift_bridge__$delete_entry(id)
 }
+public func delete_entry_result(_ id: Int64) -> RustString {
+    RustString(ptr: __swift_bridge__$delete_entry_result(id))
+}
 public func get_entry_text(_ id: Int64) -> Optional<RustString> {
     { let val = __swift_bridge__$get_entry_text(id); if val != nil { return RustString(ptr: val!) } else { return nil } }()
 }
 public func get_entry_image(_ id: Int64) -> Optional<RustVec<UInt8>> {
     { let val = __swift_bridge__$get_entry_image(id); if val != nil { return RustVec(ptr: val!) } else { return nil } }()
 }
+public func get_entry_thumbnail(_ id: Int64, _ max_px: UInt32) -> Optional<RustVec<UInt8>> {
+    { let val = __swift_bridge__$get_entry_thumbnail(id, max_px); if val != nil { return RustVec(ptr: val!) } else { return nil } }()
+}
 public func search_entries<GenericIntoRustString: IntoRustString>(_ query: GenericIntoRustString, _ limit: Int32) -> RustString {
     RustString(ptr: __swift_bridge__$search_entries({ let rustString = query.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), limit))
 }
+public func search_entries_with_snippets<GenericIntoRustString: IntoRustString>(_ query: GenericIntoRustString, _ limit: Int32) -> RustString {
+    RustString(ptr: __swift_bridge__$search_entries_with_snippets({ let rustString = query.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), limit))
+}
+public func search_entries_ranked<GenericIntoRustString: IntoRustString>(_ query: GenericIntoRustString, _ limit: Int32, _ sort_order: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$search_entries_ranked({ let rustString = query.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), limit, { let rustString = sort_order.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func search_entries_fuzzy<GenericIntoRustString: IntoRustString>(_ query: GenericIntoRustString, _ limit: Int32, _ min_hits: Int32, _ scan_budget: Int32) -> RustString {
+    RustString(ptr: __swift_bridge__$search_entries_fuzzy({ let rustString = query.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), limit, min_hits, scan_budget))
+}
 public func get_entries_before(_ before_timestamp: Int64, _ limit: Int32) -> RustString {
     RustString(ptr: __swift_bridge__$get_entries_before(before_timestamp, limit))
 }
 public func touch_entry(_ id: Int64) -> Bool {
     __swift_bridge__$touch_entry(id)
 }
+public func touch_entry_result(_ id: Int64) -> RustString {
+    RustString(ptr: __swift_bridge__$touch_entry_result(id))
+}
 public func cleanup_old_entries(_ max_age_days: Int32) -> Int64 {
     __swift_bridge__$cleanup_old_entries(max_age_days)
 }
+public func cleanup_old_entries_result(_ max_age_days: Int32) -> RustString {
+    RustString(ptr: __swift_bridge__$cleanup_old_entries_result(max_age_days))
+}
+public func set_entry_expiry(_ id: Int64, _ expires_at: Int64) -> RustString {
+    RustString(ptr: __swift_bridge__$set_entry_expiry(id, expires_at))
+}
+public func purge_expired() -> Int64 {
+    __swift_bridge__$purge_expired()
+}
+public func purge_expired_result() -> RustString {
+    RustString(ptr: __swift_bridge__$purge_expired_result())
+}
+public func apply_retention<GenericIntoRustString: IntoRustString>(_ policy_json: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$apply_retention({ let rustString = policy_json.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func pin_entry(_ id: Int64) -> RustString {
+    RustString(ptr: __swift_bridge__$pin_entry(id))
+}
+public func unpin_entry(_ id: Int64) -> RustString {
+    RustString(ptr: __swift_bridge__$unpin_entry(id))
+}
+public func get_pinned_entries() -> RustString {
+    RustString(ptr: __swift_bridge__$get_pinned_entries())
+}
+public func reorder_pins<GenericIntoRustString: IntoRustString>(_ ids_json: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$reorder_pins({ let rustString = ids_json.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func create_tag<GenericIntoRustString: IntoRustString>(_ name: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$create_tag({ let rustString = name.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func rename_tag<GenericIntoRustString: IntoRustString>(_ id: Int64, _ new_name: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$rename_tag(id, { let rustString = new_name.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func delete_tag(_ id: Int64) -> RustString {
+    RustString(ptr: __swift_bridge__$delete_tag(id))
+}
+public func list_tags() -> RustString {
+    RustString(ptr: __swift_bridge__$list_tags())
+}
+public func tag_entry(_ entry_id: Int64, _ tag_id: Int64) -> RustString {
+    RustString(ptr: __swift_bridge__$tag_entry(entry_id, tag_id))
+}
+public func untag_entry(_ entry_id: Int64, _ tag_id: Int64) -> RustString {
+    RustString(ptr: __swift_bridge__$untag_entry(entry_id, tag_id))
+}
+public func get_entries_by_tag<GenericIntoRustString: IntoRustString>(_ tag_id: Int64, _ cursor: GenericIntoRustString, _ direction: GenericIntoRustString, _ limit: Int32) -> RustString {
+    RustString(ptr: __swift_bridge__$get_entries_by_tag(tag_id, { let rustString = cursor.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = direction.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), limit))
+}
+public func list_entries<GenericIntoRustString: IntoRustString>(_ filter_json: GenericIntoRustString, _ cursor: GenericIntoRustString, _ limit: Int32) -> RustString {
+    RustString(ptr: __swift_bridge__$list_entries({ let rustString = filter_json.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = cursor.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), limit))
+}
+public func get_entries_page<GenericIntoRustString: IntoRustString>(_ filter_json: GenericIntoRustString, _ cursor: GenericIntoRustString, _ direction: GenericIntoRustString, _ limit: Int32) -> RustString {
+    RustString(ptr: __swift_bridge__$get_entries_page({ let rustString = filter_json.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = cursor.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = direction.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), limit))
+}
+public func search_entries_page<GenericIntoRustString: IntoRustString>(_ query: GenericIntoRustString, _ cursor: GenericIntoRustString, _ direction: GenericIntoRustString, _ limit: Int32) -> RustString {
+    RustString(ptr: __swift_bridge__$search_entries_page({ let rustString = query.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = cursor.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = direction.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), limit))
+}
+public func get_sensitive_rules() -> RustString {
+    RustString(ptr: __swift_bridge__$get_sensitive_rules())
+}
+public func set_sensitive_rules<GenericIntoRustString: IntoRustString>(_ rules_json: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$set_sensitive_rules({ let rustString = rules_json.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func add_ignored_app<GenericIntoRustString: IntoRustString>(_ kind: GenericIntoRustString, _ pattern: GenericIntoRustString) -> RustString {
+    RustString(ptr: __swift_bridge__$add_ignored_app({ let rustString = kind.intoRustString(); rustString.isOwned = false; return rustString.ptr }(), { let rustString = pattern.intoRustString(); rustString.isOwned = false; return rustString.ptr }()))
+}
+public func remove_ignored_app(_ id: Int64) -> RustString {
+    RustString(ptr: __swift_bridge__$remove_ignored_app(id))
+}
+public func list_ignored_apps() -> RustString {
+    RustString(ptr: __swift_bridge__$list_ignored_apps())
+}
+public func purge_ignored_app_entries(_ id: Int64) -> RustString {
+    RustString(ptr: __swift_bridge__$purge_ignored_app_entries(id))
+}
 
 
@@ -55,7 +55,9 @@ class ClipboardMonitor: ObservableObject {
             return
         }
 
-        let sourceApp = NSWorkspace.shared.frontmostApplication?.localizedName ?? ""
+        let frontmostApp = NSWorkspace.shared.frontmostApplication
+        let sourceApp = frontmostApp?.localizedName ?? ""
+        let bundleId = frontmostApp?.bundleIdentifier ?? ""
 
         if let string = pasteboard.string(forType: .string) {
             let hash = string.hashValue
@@ -79,7 +81,7 @@ class ClipboardMonitor: ObservableObject {
             let contentType = isFilePath ? "FilePath" : "PlainText"
 
             Task.detached {
-                let success = save_clipboard_entry(contentType, string, sourceApp)
+                let success = save_clipboard_entry(contentType, string, sourceApp, bundleId)
                 if !success {
                     await MainActor.run {
                         logger.error("Failed to save clipboard entry (type: \(contentType))")
@@ -97,7 +99,8 @@ class ClipboardMonitor: ObservableObject {
                     let buffer = rawBuffer.bindMemory(to: UInt8.self)
                     let success = save_clipboard_image(
                         UnsafeBufferPointer(start: buffer.baseAddress, count: buffer.count),
-                        sourceApp
+                        sourceApp,
+                        bundleId
                     )
                     if !success {
                         Task { @MainActor in
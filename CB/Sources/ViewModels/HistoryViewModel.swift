@@ -3,9 +3,28 @@ import os
 
 private let logger = Logger(subsystem: "com.otkrickey.cb", category: "HistoryViewModel")
 
+private struct FFIError: Decodable {
+    let code: Int
+    let name: String
+    let message: String
+}
+
 private struct FFIResponse<T: Decodable>: Decodable {
     let ok: T?
-    let error: String?
+    let error: FFIError?
+}
+
+/// Decodes a cb-core JSON envelope, logging the error case.
+private func decodeResult<T: Decodable>(_ json: RustString, as type: T.Type, context: String) -> T? {
+    guard let data = json.toString().data(using: .utf8),
+          let response = try? JSONDecoder().decode(FFIResponse<T>.self, from: data) else {
+        logger.error("\(context): undecodable response")
+        return nil
+    }
+    if let error = response.error {
+        logger.error("\(context) failed: [\(error.name)] \(error.message)")
+    }
+    return response.ok
 }
 
 enum ContentTypeFilter: CaseIterable {
@@ -245,7 +264,7 @@ class HistoryViewModel {
             if let entries = response.ok {
                 self.entries = entries
             } else if let error = response.error {
-                logger.error("loadEntries failed: \(error)")
+                logger.error("loadEntries failed: [\(error.name)] \(error.message)")
             }
         }
         updateFilteredEntries()
@@ -272,6 +291,18 @@ class HistoryViewModel {
         updateFilteredEntries()
     }
 
+    func pinEntry(_ id: Int64) {
+        if decodeResult(pin_entry(id), as: Bool.self, context: "pinEntry") == true {
+            loadEntries()
+        }
+    }
+
+    func unpinEntry(_ id: Int64) {
+        if decodeResult(unpin_entry(id), as: Bool.self, context: "unpinEntry") == true {
+            loadEntries()
+        }
+    }
+
     func deleteEntry(_ id: Int64) {
         let deleted = delete_entry(id)
         if deleted {
@@ -0,0 +1,438 @@
+//! Runs a parsed command against the database. Results go to stdout as
+//! text, or as one JSON document with `--json`; `copy-out` and `export`
+//! to stdout write raw content instead.
+//!
+//! Entries are saved the way the app saves copies: through the sensitive
+//! rules and ignore list, merging identical content.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use cb_core::cipher::{Argon2Params, CipherConfig, KeySpec};
+use cb_core::export::ImageExport;
+use cb_core::models::{ContentType, EntryFilter, SaveOutcome};
+use cb_core::storage::Storage;
+use cb_core::thumbnail;
+use cb_core::{Error, Result};
+use serde::Serialize;
+
+use crate::format;
+use crate::{AddArgs, Cli, Command, KeyType, ListArgs};
+
+/// The process's standard streams, swapped out in tests.
+pub struct Io<'a> {
+    pub stdin: &'a mut dyn BufRead,
+    pub stdout: &'a mut dyn Write,
+    /// Binary output is refused when this is set.
+    pub stdout_is_terminal: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Deleted {
+    deleted: Vec<i64>,
+    missing: Vec<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct Pinned {
+    id: i64,
+    pinned: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Removed {
+    removed: u64,
+}
+
+fn default_db_path() -> Result<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .ok_or_else(|| Error::InvalidInput("HOME is not set; pass --db".to_string()))?;
+    Ok(PathBuf::from(home).join("Library/Application Support/CB/clipboard.db"))
+}
+
+fn first_line(input: &mut dyn BufRead) -> Result<String> {
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// The key from `--key-file`, `--key-stdin` or `CB_KEY`, in that order.
+/// An empty key file or line is an error rather than "unencrypted".
+fn resolve_key(cli: &Cli, env_key: Option<String>, stdin: &mut dyn BufRead) -> Result<Option<String>> {
+    let key = if let Some(path) = &cli.key_file {
+        first_line(&mut BufReader::new(File::open(path)?))?
+    } else if cli.key_stdin {
+        first_line(stdin)?
+    } else {
+        return Ok(env_key);
+    };
+    if key.is_empty() {
+        return Err(Error::InvalidInput("the key is empty".to_string()));
+    }
+    Ok(Some(key))
+}
+
+/// The cipher settings from the command line, keyed as `--key-type` says.
+/// Argon2id's cost parameters only matter when `--create` makes the salt
+/// file; afterwards the stored ones are used.
+fn cipher_config(cli: &Cli, key: Option<String>) -> CipherConfig {
+    let key = key.map(|key| match cli.key_type {
+        KeyType::Passphrase => KeySpec::Passphrase(key),
+        KeyType::RawHex => KeySpec::RawHex(key),
+        KeyType::Argon2id => KeySpec::Argon2id { passphrase: key, params: Argon2Params::default() },
+    });
+    CipherConfig {
+        key,
+        cipher_page_size: cli.cipher_page_size,
+        kdf_iter: cli.kdf_iter,
+        cipher_compatibility: cli.cipher_compatibility,
+    }
+}
+
+fn open(cli: &Cli, key: Option<String>) -> Result<Storage> {
+    let path = match &cli.db {
+        Some(path) => path.clone(),
+        None => default_db_path()?,
+    };
+    // A mistyped path should not leave an empty database behind
+    if !cli.create && !path.exists() {
+        return Err(Error::Io(format!("no database at {}; pass --create to make one", path.display())));
+    }
+    let path = path.to_str()
+        .ok_or_else(|| Error::InvalidInput(format!("database path is not UTF-8: {}", path.display())))?;
+    let config = cipher_config(cli, key);
+    match Storage::open(path, &config) {
+        // Without a key SQLCipher reads an encrypted file as garbage
+        Err(Error::Corrupt(_)) if config.key.is_none() => Err(Error::WrongKey),
+        result => result,
+    }
+}
+
+/// Writes `value` as JSON, or whatever `human` renders.
+fn emit<T: Serialize>(json: bool, out: &mut dyn Write, value: &T, human: impl FnOnce(&T) -> String) -> Result<()> {
+    if json {
+        serde_json::to_writer(&mut *out, value)?;
+        out.write_all(b"\n")?;
+    } else {
+        out.write_all(human(value).as_bytes())?;
+    }
+    Ok(())
+}
+
+pub fn run(cli: Cli, env_key: Option<String>, io: &mut Io) -> Result<()> {
+    let key = resolve_key(&cli, env_key, io.stdin)?;
+    let storage = open(&cli, key)?;
+    let json = cli.json;
+    let out = &mut *io.stdout;
+
+    match cli.command {
+        Command::List(args) => {
+            let entries = list(&storage, &args)?;
+            emit(json, out, &entries, |entries| {
+                entries.iter().map(|entry| format::entry_line(entry, None) + "\n").collect()
+            })
+        }
+        Command::Search { query, limit } => {
+            let hits = storage.search_entries_with_snippets(&query, limit)?;
+            emit(json, out, &hits, |hits| {
+                hits.iter().map(|hit| format::entry_line(&hit.entry, hit.snippet.as_deref()) + "\n").collect()
+            })
+        }
+        Command::Show { id } => {
+            let entry = storage.get_entry(id)?.ok_or(Error::NotFound)?;
+            emit(json, out, &entry, format::entry_details)
+        }
+        Command::CopyOut { id, output } => {
+            let entry = storage.get_entry(id)?.ok_or(Error::NotFound)?;
+            let data = match entry.content_type {
+                ContentType::Image => storage.get_entry_image(id)?.ok_or(Error::NotFound)?,
+                _ => entry.text_content.unwrap_or_default().into_bytes(),
+            };
+            match output {
+                Some(path) => std::fs::write(path, data)?,
+                None if entry.content_type == ContentType::Image && io.stdout_is_terminal => {
+                    return Err(Error::InvalidInput(
+                        "refusing to write image data to a terminal; pipe it or pass -o".to_string(),
+                    ));
+                }
+                None => out.write_all(&data)?,
+            }
+            Ok(())
+        }
+        Command::Add(args) => {
+            let outcome = add(&storage, &args, io.stdin)?;
+            emit(json, out, &outcome, |outcome| match outcome {
+                SaveOutcome::Stored { id } => format!("{id}\n"),
+                SaveOutcome::Skipped { rule } => format!("skipped by sensitive rule {rule}\n"),
+                SaveOutcome::Ignored { pattern } => format!("ignored by rule {pattern}\n"),
+            })
+        }
+        Command::Delete { ids } => {
+            let mut result = Deleted { deleted: vec![], missing: vec![] };
+            for id in ids {
+                if storage.delete_entry(id)? {
+                    result.deleted.push(id);
+                } else {
+                    result.missing.push(id);
+                }
+            }
+            emit(json, out, &result, |result| {
+                let mut text = format!("deleted {}\n", result.deleted.len());
+                for id in &result.missing {
+                    text.push_str(&format!("no entry {id}\n"));
+                }
+                text
+            })
+        }
+        Command::Pin { id, unpin } => {
+            let found = if unpin { storage.unpin_entry(id)? } else { storage.pin_entry(id)? };
+            if !found {
+                return Err(Error::NotFound);
+            }
+            emit(json, out, &Pinned { id, pinned: !unpin }, |_| String::new())
+        }
+        Command::Export { output, image_dir } => {
+            let images = match &image_dir {
+                Some(dir) => ImageExport::Files(dir),
+                None => ImageExport::Inline,
+            };
+            let Some(path) = output else {
+                storage.export_history(&mut &mut *out, images)?;
+                return Ok(());
+            };
+            let report = export_to_file(&storage, &path, images)?;
+            emit(json, out, &report, |report| {
+                format!("exported {} entries, {} images\n", report.entries, report.images)
+            })
+        }
+        Command::Import { path, image_dir, from } => {
+            let report = match from {
+                Some(source) => storage.import_from(source, &path)?,
+                None if path == Path::new("-") => storage.import_history(&mut *io.stdin, image_dir.as_deref())?,
+                None => storage.import_history(BufReader::new(File::open(&path)?), image_dir.as_deref())?,
+            };
+            emit(json, out, &report, |report| {
+                let mut line = format!("inserted {}, merged {}, skipped {}", report.inserted, report.merged, report.skipped);
+                if report.estimated_times > 0 {
+                    line += &format!(", {} timed by file mtime", report.estimated_times);
+                }
+                if report.unknown_sources > 0 {
+                    line += &format!(", {} without source app", report.unknown_sources);
+                }
+                line + "\n"
+            })
+        }
+        Command::Stats => emit(json, out, &storage.stats()?, format::stats),
+        Command::Cleanup { days, expired_only } => {
+            let removed = if expired_only { storage.purge_expired()? } else { storage.cleanup_old_entries(days)? };
+            emit(json, out, &Removed { removed }, |r| format!("removed {}\n", r.removed))
+        }
+    }
+}
+
+fn list(storage: &Storage, args: &ListArgs) -> Result<Vec<cb_core::models::ClipboardEntry>> {
+    if args.pinned {
+        let mut pinned = storage.get_pinned_entries()?;
+        pinned.truncate(args.limit.max(0) as usize);
+        return Ok(pinned);
+    }
+    let filter = EntryFilter {
+        content_types: args.content_types.clone(),
+        source_apps: args.apps.clone(),
+        ..Default::default()
+    };
+    Ok(storage.list_entries(&filter, None, args.limit)?.items)
+}
+
+fn add(storage: &Storage, args: &AddArgs, stdin: &mut dyn BufRead) -> Result<SaveOutcome> {
+    let mut data = Vec::new();
+    stdin.read_to_end(&mut data)?;
+    if data.is_empty() {
+        return Err(Error::InvalidInput("nothing to add: stdin is empty".to_string()));
+    }
+    if let Some(app) = storage.find_ignored_app(&args.app, &args.bundle_id)? {
+        return Ok(SaveOutcome::Ignored { pattern: app.pattern });
+    }
+    if args.content_type == ContentType::Image {
+        let thumb = thumbnail::generate(&data, thumbnail::STORED_MAX_PX).ok();
+        return storage.insert_image_entry_with_thumbnail(&data, &args.app, &args.bundle_id, thumb);
+    }
+    let text = String::from_utf8(data)
+        .map_err(|_| Error::InvalidInput("stdin is not UTF-8; use --type Image for image data".to_string()))?;
+    storage.insert_text_entry_from(&args.content_type, &text, &args.app, &args.bundle_id, None)
+}
+
+/// Exports to `path`, removing the partial file if anything fails.
+fn export_to_file(storage: &Storage, path: &Path, images: ImageExport) -> Result<cb_core::export::ExportReport> {
+    let result = File::create(path).map_err(Error::from).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        let report = storage.export_history(&mut writer, images)?;
+        writer.flush()?;
+        Ok(report)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// A scratch directory under the temp dir, emptied first.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cb_cli_test_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Runs `cb --db <db> <args>` with `stdin`, returning stdout.
+    fn cb(db: &Path, args: &[&str], stdin: &[u8]) -> Result<Vec<u8>> {
+        cb_with(db, args, stdin, None, false)
+    }
+
+    fn cb_with(db: &Path, args: &[&str], stdin: &[u8], env_key: Option<&str>, tty: bool) -> Result<Vec<u8>> {
+        let db = db.to_str().unwrap();
+        let cli = Cli::try_parse_from(["cb", "--db", db].iter().chain(args)).unwrap();
+        let mut stdin = stdin;
+        let mut stdout = Vec::new();
+        let mut io = Io { stdin: &mut stdin, stdout: &mut stdout, stdout_is_terminal: tty };
+        run(cli, env_key.map(str::to_string), &mut io)?;
+        Ok(stdout)
+    }
+
+    fn json(output: Vec<u8>) -> serde_json::Value {
+        serde_json::from_slice(&output).unwrap()
+    }
+
+    #[test]
+    fn test_add_list_show() {
+        let db = scratch("add_list").join("clipboard.db");
+        assert!(matches!(cb(&db, &["list"], b""), Err(Error::Io(_))));
+        assert!(!db.exists());
+
+        let id = String::from_utf8(cb(&db, &["--create", "add"], b"git status").unwrap()).unwrap();
+        cb(&db, &["add", "--type", "FilePath", "--app", "Finder"], b"/tmp/a.txt").unwrap();
+        assert!(matches!(cb(&db, &["add"], b""), Err(Error::InvalidInput(_))));
+        assert!(matches!(cb(&db, &["add"], &[0xff, 0xfe]), Err(Error::InvalidInput(_))));
+
+        let listed = json(cb(&db, &["--json", "list", "--app", "Finder"], b"").unwrap());
+        assert_eq!(listed.as_array().unwrap().len(), 1);
+        assert_eq!(listed[0]["text_content"], "/tmp/a.txt");
+
+        let lines = String::from_utf8(cb(&db, &["list"], b"").unwrap()).unwrap();
+        let first = lines.lines().last().unwrap();
+        assert!(first.starts_with(&format!("{}\t", id.trim())) && first.ends_with("\tPlainText\tgit status"));
+
+        let shown = json(cb(&db, &["--json", "show", id.trim()], b"").unwrap());
+        assert_eq!(shown["source_app"], "cb");
+        assert!(matches!(cb(&db, &["show", "999"], b""), Err(Error::NotFound)));
+
+        let hits = json(cb(&db, &["--json", "search", "status"], b"").unwrap());
+        assert_eq!(hits[0]["text_content"], "git status");
+    }
+
+    #[test]
+    fn test_keys() {
+        let dir = scratch("keys");
+        let db = dir.join("clipboard.db");
+        cb_with(&db, &["--create", "add"], b"secret", Some("env-key"), false).unwrap();
+        assert!(matches!(cb(&db, &["list"], b""), Err(Error::WrongKey)));
+
+        // The rest of stdin after the key line is the entry
+        let added = cb(&db, &["--json", "--key-stdin", "add"], b"env-key\nfrom stdin").unwrap();
+        assert_eq!(json(added)["status"], "stored");
+
+        let key_file = dir.join("key");
+        std::fs::write(&key_file, "env-key\n").unwrap();
+        let key_file = key_file.to_str().unwrap();
+        let listed = json(cb(&db, &["--json", "--key-file", key_file, "list"], b"").unwrap());
+        assert_eq!(listed.as_array().unwrap().len(), 2);
+
+        std::fs::write(key_file, "").unwrap();
+        let result = cb_with(&db, &["--key-file", key_file, "list"], b"", Some("env-key"), false);
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_key_types_and_cipher_settings() {
+        let dir = scratch("key_types");
+        let raw = "0123456789abcdef".repeat(4);
+        let db = dir.join("raw.db");
+        cb_with(&db, &["--create", "--key-type", "raw-hex", "add"], b"one", Some(&raw), false).unwrap();
+        let listed = json(cb_with(&db, &["--json", "--key-type", "raw-hex", "list"], b"", Some(&raw), false).unwrap());
+        assert_eq!(listed[0]["text_content"], "one");
+        // The same digits as a passphrase derive a different key
+        assert!(matches!(cb_with(&db, &["list"], b"", Some(&raw), false), Err(Error::WrongKey)));
+        let short = cb_with(&db, &["--key-type", "raw-hex", "list"], b"", Some("abcd"), false);
+        assert!(matches!(short, Err(Error::InvalidInput(_))));
+
+        let db = dir.join("paged.db");
+        cb_with(&db, &["--create", "--cipher-page-size", "8192", "add"], b"two", Some("key"), false).unwrap();
+        cb_with(&db, &["--cipher-page-size", "8192", "list"], b"", Some("key"), false).unwrap();
+        assert!(matches!(cb_with(&db, &["list"], b"", Some("key"), false), Err(Error::WrongKey)));
+        // Cipher settings without a key are refused rather than ignored
+        assert!(matches!(cb(&db, &["--kdf-iter", "1000", "list"], b""), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_copy_out_pin_delete() {
+        let dir = scratch("copy_out");
+        let db = dir.join("clipboard.db");
+        let image = b"\x89PNG not really";
+        let id = json(cb(&db, &["--json", "--create", "add", "--type", "Image"], image).unwrap())["id"].clone();
+        let id = id.to_string();
+
+        assert_eq!(cb(&db, &["copy-out", &id], b"").unwrap(), image);
+        let tty = cb_with(&db, &["copy-out", &id], b"", None, true);
+        assert!(matches!(tty, Err(Error::InvalidInput(_))));
+        let file = dir.join("out.png");
+        cb_with(&db, &["copy-out", &id, "-o", file.to_str().unwrap()], b"", None, true).unwrap();
+        assert_eq!(std::fs::read(&file).unwrap(), image);
+
+        cb(&db, &["pin", &id], b"").unwrap();
+        let pinned = json(cb(&db, &["--json", "list", "--pinned"], b"").unwrap());
+        assert_eq!(pinned[0]["id"].to_string(), id);
+        cb(&db, &["pin", &id, "--unpin"], b"").unwrap();
+        assert!(matches!(cb(&db, &["pin", "999"], b""), Err(Error::NotFound)));
+
+        let deleted = json(cb(&db, &["--json", "delete", &id, "999"], b"").unwrap());
+        assert_eq!(deleted["deleted"].to_string(), format!("[{id}]"));
+        assert_eq!(deleted["missing"], serde_json::json!([999]));
+    }
+
+    #[test]
+    fn test_export_import_stats_cleanup() {
+        let dir = scratch("export");
+        let source = dir.join("source.db");
+        cb(&source, &["--create", "add"], b"one").unwrap();
+        cb(&source, &["add"], b"two").unwrap();
+
+        let exported = cb(&source, &["export"], b"").unwrap();
+        assert_eq!(exported.split(|b| *b == b'\n').filter(|l| !l.is_empty()).count(), 3);
+        let file = dir.join("history.ndjson");
+        let report = json(cb(&source, &["--json", "export", "-o", file.to_str().unwrap()], b"").unwrap());
+        assert_eq!(report["entries"], 2);
+
+        let target = dir.join("target.db");
+        let report = json(cb(&target, &["--json", "--create", "import"], &exported).unwrap());
+        assert_eq!(report["inserted"], 2);
+        let report = json(cb(&target, &["--json", "import", file.to_str().unwrap()], b"").unwrap());
+        assert_eq!(report["merged"], 2);
+
+        let stats = json(cb(&target, &["--json", "stats"], b"").unwrap());
+        assert_eq!((stats["entries"].as_u64(), stats["text_bytes"].as_u64()), (Some(2), Some(6)));
+        let human = String::from_utf8(cb(&target, &["stats"], b"").unwrap()).unwrap();
+        assert!(human.starts_with("entries      2 (0 pinned)\n  PlainText  2\n"));
+
+        let removed = json(cb(&target, &["--json", "cleanup", "--expired-only"], b"").unwrap());
+        assert_eq!(removed["removed"], 0);
+        // Entries created this millisecond are not yet older than zero days
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert_eq!(cb(&target, &["cleanup", "--days", "0"], b"").unwrap(), b"removed 2\n");
+    }
+}
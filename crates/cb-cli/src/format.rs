@@ -0,0 +1,146 @@
+//! Human-readable output. Lists are tab-separated so they still cut and
+//! sort in a pipeline; `--json` skips all of this.
+
+use cb_core::models::{ClipboardEntry, ContentType, HistoryStats};
+
+const PREVIEW_CHARS: usize = 80;
+
+/// Milliseconds since the epoch as UTC ISO 8601, to the second.
+pub fn timestamp(millis: i64) -> String {
+    let seconds = millis.div_euclid(1000);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    let time = seconds.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Proleptic Gregorian date of a day count from 1970-01-01, after Howard
+/// Hinnant's `civil_from_days`.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+pub fn bytes(n: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if n < 1024 {
+        return format!("{n} B");
+    }
+    let mut value = n as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// One line of text: whitespace runs collapsed, cut at `PREVIEW_CHARS`.
+pub fn preview(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", &collapsed[..end]),
+        None => collapsed,
+    }
+}
+
+fn entry_preview(entry: &ClipboardEntry) -> String {
+    match (&entry.content_type, entry.image_width, entry.image_height) {
+        (ContentType::Image, Some(width), Some(height)) => format!("[image {width}x{height}]"),
+        (ContentType::Image, _, _) => "[image]".to_string(),
+        _ => preview(entry.text_content.as_deref().unwrap_or_default()),
+    }
+}
+
+/// `id  created  type  preview`, tab-separated; `snippet` replaces the
+/// preview for search hits.
+pub fn entry_line(entry: &ClipboardEntry, snippet: Option<&str>) -> String {
+    let text = snippet.map_or_else(|| entry_preview(entry), preview);
+    format!("{}\t{}\t{}\t{text}", entry.id, timestamp(entry.created_at), entry.content_type.as_str())
+}
+
+/// Metadata as `key: value` lines, then the full text after a blank line.
+pub fn entry_details(entry: &ClipboardEntry) -> String {
+    let mut fields = vec![
+        ("id", entry.id.to_string()),
+        ("type", entry.content_type.as_str().to_string()),
+        ("created", timestamp(entry.created_at)),
+        ("first copied", timestamp(entry.first_copied_at)),
+        ("copies", entry.copy_count.to_string()),
+    ];
+    if let Some(app) = &entry.source_app {
+        fields.push(("app", app.clone()));
+    }
+    if entry.pinned {
+        fields.push(("pinned", "yes".to_string()));
+    }
+    if !entry.tags.is_empty() {
+        fields.push(("tags", entry.tags.join(", ")));
+    }
+    if let (Some(width), Some(height)) = (entry.image_width, entry.image_height) {
+        fields.push(("size", format!("{width}x{height}")));
+    }
+    if !entry.sensitive.is_empty() {
+        fields.push(("sensitive", entry.sensitive.join(", ")));
+    }
+    if let Some(expires_at) = entry.expires_at {
+        fields.push(("expires", timestamp(expires_at)));
+    }
+
+    let mut out: String = fields.iter().map(|(key, value)| format!("{key:<13}{value}\n")).collect();
+    if let Some(text) = &entry.text_content {
+        out.push('\n');
+        out.push_str(text);
+        out.push('\n');
+    }
+    out
+}
+
+pub fn stats(stats: &HistoryStats) -> String {
+    let mut lines = vec![format!("entries      {} ({} pinned)", stats.entries, stats.pinned)];
+    lines.extend(stats.by_type.iter().map(|(name, count)| format!("  {name:<11}{count}")));
+    lines.push(format!("tags         {}", stats.tags));
+    lines.push(format!("text         {}", bytes(stats.text_bytes)));
+    lines.push(format!("images       {}", bytes(stats.image_bytes)));
+    if let (Some(oldest), Some(newest)) = (stats.oldest_at, stats.newest_at) {
+        lines.push(format!("oldest       {}", timestamp(oldest)));
+        lines.push(format!("newest       {}", timestamp(newest)));
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(timestamp(1_704_153_600_000), "2024-01-02T00:00:00Z");
+        // Leap day, and a time of day
+        assert_eq!(timestamp(1_709_210_096_789), "2024-02-29T12:34:56Z");
+        assert_eq!(timestamp(-1000), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview("  git\tstatus\n\n-s "), "git status -s");
+        let long = "é".repeat(PREVIEW_CHARS + 5);
+        assert_eq!(preview(&long), format!("{}…", "é".repeat(PREVIEW_CHARS)));
+        assert_eq!(bytes(512), "512 B");
+        assert_eq!(bytes(1536), "1.5 KB");
+        assert_eq!(bytes(5 * 1024 * 1024), "5.0 MB");
+    }
+}
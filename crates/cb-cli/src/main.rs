@@ -0,0 +1,230 @@
+//! `cb`, a headless client for the history database the app writes, so the
+//! history can be scripted in shell pipelines. See `commands` for what each
+//! subcommand does.
+
+mod commands;
+mod format;
+
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use cb_core::Error;
+use cb_core::import::ImportSource;
+use cb_core::models::ContentType;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::commands::Io;
+
+#[derive(Debug, Parser)]
+#[command(name = "cb", version, about = "Read and edit the CB clipboard history")]
+#[command(after_help = "The key comes from --key-file, --key-stdin or the CB_KEY environment variable, \
+    in that order; without one the database is opened unencrypted. To use the app's key:\n\n  \
+    security find-generic-password -s com.otkrickey.cb.db-encryption -a clipboard-db-key -w | cb --key-stdin list\n\n\
+    A database keyed another way needs the same --key-type and cipher settings it was created with; \
+    with different ones it fails as a wrong key.")]
+pub struct Cli {
+    /// Database file [default: ~/Library/Application Support/CB/clipboard.db]
+    #[arg(long, env = "CB_DB", global = true)]
+    pub db: Option<PathBuf>,
+    /// Read the key from the first line of this file
+    #[arg(long, global = true, conflicts_with = "key_stdin")]
+    pub key_file: Option<PathBuf>,
+    /// Read the key from the first line of stdin; the rest is input as usual
+    #[arg(long, global = true)]
+    pub key_stdin: bool,
+    /// How the key is used: as a SQLCipher passphrase, as 64 hex digits of raw
+    /// key, or as a passphrase derived with Argon2id and the salt in `<db>.kdf`
+    #[arg(long, global = true, value_enum, default_value_t = KeyType::Passphrase)]
+    pub key_type: KeyType,
+    /// SQLCipher `cipher_page_size` the database was created with
+    #[arg(long, global = true)]
+    pub cipher_page_size: Option<u32>,
+    /// SQLCipher `kdf_iter` the database was created with
+    #[arg(long, global = true)]
+    pub kdf_iter: Option<u32>,
+    /// SQLCipher major version (1-4) whose defaults the database uses
+    #[arg(long, global = true)]
+    pub cipher_compatibility: Option<u32>,
+    /// Create the database if it does not exist
+    #[arg(long, global = true)]
+    pub create: bool,
+    /// Print results, and errors on stderr, as JSON
+    #[arg(long, global = true)]
+    pub json: bool,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeyType {
+    Passphrase,
+    RawHex,
+    Argon2id,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List entries, newest first
+    List(ListArgs),
+    /// Full-text search, newest first
+    Search {
+        query: String,
+        #[arg(long, default_value_t = 20)]
+        limit: i32,
+    },
+    /// Show one entry with its metadata
+    Show { id: i64 },
+    /// Write an entry's text or image bytes to stdout or a file
+    CopyOut {
+        id: i64,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add an entry read from stdin
+    Add(AddArgs),
+    /// Delete entries
+    Delete {
+        #[arg(required = true)]
+        ids: Vec<i64>,
+    },
+    /// Pin an entry, or unpin it with --unpin
+    Pin {
+        id: i64,
+        #[arg(long)]
+        unpin: bool,
+    },
+    /// Export the history as NDJSON to stdout or a file
+    Export {
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Write images here as files instead of inline base64
+        #[arg(long)]
+        image_dir: Option<PathBuf>,
+    },
+    /// Merge an export, or another manager's history with --from
+    Import {
+        /// Export file, or `-` for stdin; with --from, the other manager's data
+        #[arg(default_value = "-")]
+        path: PathBuf,
+        /// Folder holding an export's image files
+        #[arg(long, conflicts_with = "from")]
+        image_dir: Option<PathBuf>,
+        /// Maccy, Clipy or CopyQ
+        #[arg(long, value_parser = parse_source)]
+        from: Option<ImportSource>,
+    },
+    /// Count entries, tags and stored bytes
+    Stats,
+    /// Delete unpinned entries older than --days, and expired ones
+    Cleanup {
+        #[arg(long, default_value_t = 7)]
+        days: i32,
+        /// Only delete entries past their expiry
+        #[arg(long, conflicts_with = "days")]
+        expired_only: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    #[arg(long, default_value_t = 20)]
+    pub limit: i32,
+    /// Only this type; repeat for several
+    #[arg(long = "type", value_parser = parse_content_type)]
+    pub content_types: Vec<ContentType>,
+    /// Only entries copied from this app; repeat for several
+    #[arg(long = "app")]
+    pub apps: Vec<String>,
+    /// Only pinned entries, in pin order
+    #[arg(long, conflicts_with_all = ["content_types", "apps"])]
+    pub pinned: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct AddArgs {
+    /// `Image` stores stdin as image bytes; the others expect UTF-8 text
+    #[arg(long = "type", default_value = "PlainText", value_parser = parse_content_type)]
+    pub content_type: ContentType,
+    /// Source app recorded with the entry and checked against ignore rules
+    #[arg(long, default_value = "cb")]
+    pub app: String,
+    /// Bundle id checked against ignore rules
+    #[arg(long, default_value = "")]
+    pub bundle_id: String,
+}
+
+fn parse_content_type(s: &str) -> Result<ContentType, String> {
+    match s {
+        "PlainText" | "RichText" | "Image" | "FilePath" => Ok(ContentType::from_str(s)),
+        _ => Err("expected PlainText, RichText, Image or FilePath".to_string()),
+    }
+}
+
+fn parse_source(s: &str) -> Result<ImportSource, String> {
+    ImportSource::parse(s).map_err(|e| e.to_string())
+}
+
+/// Stdout that remembers whether the reader went away, since `Error::Io`
+/// keeps only the message. `cb list | head` closes the pipe early; that is
+/// not a failure.
+struct PipeWriter<W> {
+    inner: W,
+    closed: bool,
+}
+
+impl<W: Write> PipeWriter<W> {
+    fn note<T>(&mut self, result: io::Result<T>) -> io::Result<T> {
+        if matches!(&result, Err(e) if e.kind() == io::ErrorKind::BrokenPipe) {
+            self.closed = true;
+        }
+        result
+    }
+}
+
+impl<W: Write> Write for PipeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = self.inner.write(buf);
+        self.note(result)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let result = self.inner.flush();
+        self.note(result)
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let json = cli.json;
+    let env_key = std::env::var("CB_KEY").ok().filter(|key| !key.is_empty());
+
+    let stdout = std::io::stdout();
+    let stdout_is_terminal = stdout.is_terminal();
+    let mut stdout = BufWriter::new(PipeWriter { inner: stdout.lock(), closed: false });
+    let mut stdin = std::io::stdin().lock();
+    let mut io = Io { stdin: &mut stdin, stdout: &mut stdout, stdout_is_terminal };
+    let result = commands::run(cli, env_key, &mut io).and_then(|()| Ok(stdout.flush()?));
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(_) if stdout.get_ref().closed => ExitCode::SUCCESS,
+        Err(e) if json => {
+            let error = serde_json::json!({
+                "error": { "code": e.code(), "name": e.name(), "message": e.to_string() }
+            });
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("cb: {e}");
+            if matches!(e, Error::WrongKey) {
+                eprintln!(
+                    "cb: pass the key with CB_KEY, --key-file or --key-stdin, with the --key-type and cipher \
+                     settings the database was created with"
+                );
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
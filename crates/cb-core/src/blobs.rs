@@ -0,0 +1,107 @@
+//! Content-addressed storage for image bytes.
+//!
+//! Blobs live in the `blobs` table keyed by the SHA-256 of their bytes, so
+//! `clipboard_entries` rows stay small and list queries never touch image
+//! pages. Entries reference a blob through `blob_hash`; triggers on
+//! `clipboard_entries` keep `refcount` in step and delete a blob when its
+//! last entry goes, whichever code path deletes the entry.
+//!
+//! Text stays inline: the FTS5 tables use `clipboard_entries.text_content`
+//! as their external content, so moving large texts out would break
+//! indexing, snippets and deletes.
+
+use rusqlite::{Connection, params};
+use sha2::{Digest, Sha256};
+
+/// Schema for the blob store, shared by the migration and its tests.
+pub(crate) const SCHEMA: &str =
+    "CREATE TABLE IF NOT EXISTS blobs (
+        hash        TEXT PRIMARY KEY,
+        data        BLOB NOT NULL,
+        size        INTEGER NOT NULL,
+        refcount    INTEGER NOT NULL DEFAULT 0,
+        created_at  INTEGER NOT NULL
+    );
+
+    CREATE TRIGGER IF NOT EXISTS clipboard_entries_blob_ai
+    AFTER INSERT ON clipboard_entries
+    WHEN new.blob_hash IS NOT NULL
+    BEGIN
+        UPDATE blobs SET refcount = refcount + 1 WHERE hash = new.blob_hash;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS clipboard_entries_blob_au
+    AFTER UPDATE OF blob_hash ON clipboard_entries
+    WHEN old.blob_hash IS NOT new.blob_hash
+    BEGIN
+        UPDATE blobs SET refcount = refcount + 1 WHERE hash = new.blob_hash;
+        UPDATE blobs SET refcount = refcount - 1 WHERE hash = old.blob_hash;
+        DELETE FROM blobs WHERE hash = old.blob_hash AND refcount <= 0;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS clipboard_entries_blob_ad
+    AFTER DELETE ON clipboard_entries
+    WHEN old.blob_hash IS NOT NULL
+    BEGIN
+        UPDATE blobs SET refcount = refcount - 1 WHERE hash = old.blob_hash;
+        DELETE FROM blobs WHERE hash = old.blob_hash AND refcount <= 0;
+    END;";
+
+/// SHA-256 of `data`, hex encoded.
+pub fn hash(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Stores `data` unless a blob with the same hash exists, returning the hash.
+/// The new blob starts unreferenced; pointing an entry's `blob_hash` at it
+/// takes the reference, so call this in the same transaction.
+pub(crate) fn put(conn: &Connection, data: &[u8], now: i64) -> rusqlite::Result<String> {
+    let hash = hash(data);
+    conn.execute(
+        "INSERT OR IGNORE INTO blobs (hash, data, size, refcount, created_at) VALUES (?1, ?2, ?3, 0, ?4)",
+        params![hash, data, data.len() as i64, now],
+    )?;
+    Ok(hash)
+}
+
+/// Deletes blobs no entry references, e.g. left behind by an interrupted
+/// write. Returns the number removed.
+pub(crate) fn collect_garbage(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.execute(
+        "DELETE FROM blobs
+         WHERE refcount <= 0
+           AND NOT EXISTS (SELECT 1 FROM clipboard_entries WHERE blob_hash = blobs.hash)",
+        [],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::OptionalExtension;
+
+    fn get(conn: &Connection, hash: &str) -> Option<Vec<u8>> {
+        conn.query_row("SELECT data FROM blobs WHERE hash = ?1", params![hash], |row| row.get(0))
+            .optional()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_put_is_content_addressed() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE clipboard_entries (id INTEGER PRIMARY KEY, blob_hash TEXT);").unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+
+        let a = put(&conn, b"image bytes", 1).unwrap();
+        assert_eq!(put(&conn, b"image bytes", 2).unwrap(), a);
+        assert_eq!(a, hash(b"image bytes"));
+        assert_eq!(get(&conn, &a).as_deref(), Some(b"image bytes".as_slice()));
+
+        // Unreferenced blobs are collected; referenced ones stay
+        let b = put(&conn, b"other", 1).unwrap();
+        conn.execute("INSERT INTO clipboard_entries (blob_hash) VALUES (?1)", params![b]).unwrap();
+        assert_eq!(collect_garbage(&conn).unwrap(), 1);
+        assert!(get(&conn, &a).is_none());
+        assert!(get(&conn, &b).is_some());
+    }
+}
@@ -0,0 +1,367 @@
+//! How an encrypted database is keyed and which SQLCipher settings it uses.
+//!
+//! A `KeySpec` becomes the value given to `PRAGMA key`:
+//!
+//! - `Passphrase` is passed as is; SQLCipher derives the key with PBKDF2
+//!   using `kdf_iter` rounds and the salt in the file's first 16 bytes
+//! - `RawHex` is a 256-bit key given as 64 hex digits, passed as `x'…'` so
+//!   SQLCipher skips its own derivation
+//! - `Argon2id` derives a raw key from a passphrase; the salt and cost
+//!   parameters are kept next to the database in `<db_path>.kdf`
+//!
+//! Rotating, backing up and restoring take a `KeySpec` too. A file written
+//! under a new key gets a `FreshKey`, whose Argon2id salt file is written
+//! only once the file itself is in place.
+//!
+//! Leaving `cipher_page_size`, `kdf_iter` and `cipher_compatibility` unset
+//! uses the defaults of the bundled SQLCipher. Setting them pins the file
+//! format, so the database can be opened by the `sqlcipher` shell or on
+//! another platform with the same pragmas. They must match on every open;
+//! a mismatch fails with `WrongKey`.
+
+use std::path::{Path, PathBuf};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+const KDF_FILE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeySpec {
+    Passphrase(String),
+    RawHex(String),
+    Argon2id {
+        passphrase: String,
+        /// Only used when the salt file is created; afterwards the stored
+        /// parameters win so the same key is derived every time.
+        #[serde(default)]
+        params: Argon2Params,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Argon2Params {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Params {
+    /// OWASP's first recommended Argon2id configuration.
+    fn default() -> Self {
+        Argon2Params { memory_kib: 19 * 1024, iterations: 2, parallelism: 1 }
+    }
+}
+
+/// Contents of `<db_path>.kdf`. The salt is not secret.
+#[derive(Debug, Serialize, Deserialize)]
+struct KdfFile {
+    version: u32,
+    salt: String,
+    #[serde(flatten)]
+    params: Argon2Params,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CipherConfig {
+    /// `None` opens the database unencrypted.
+    pub key: Option<KeySpec>,
+    pub cipher_page_size: Option<u32>,
+    pub kdf_iter: Option<u32>,
+    /// SQLCipher major version (1–4) whose defaults to use. Applied before
+    /// `cipher_page_size` and `kdf_iter`, which override it.
+    pub cipher_compatibility: Option<u32>,
+}
+
+impl KeySpec {
+    /// The value for `PRAGMA key` / `PRAGMA rekey`. `db_path` locates the
+    /// Argon2id salt file, which is created if the database does not exist
+    /// yet.
+    pub fn pragma_value(&self, db_path: &Path) -> Result<String> {
+        match self {
+            KeySpec::Passphrase(passphrase) => {
+                if passphrase.is_empty() {
+                    return Err(Error::InvalidInput("passphrase is empty".to_string()));
+                }
+                Ok(passphrase.clone())
+            }
+            KeySpec::RawHex(hex) => {
+                let key = from_hex(hex)
+                    .filter(|key| key.len() == KEY_LEN)
+                    .ok_or_else(|| Error::InvalidInput(
+                        "raw key must be 64 hex digits".to_string(),
+                    ))?;
+                Ok(raw_key(&key))
+            }
+            KeySpec::Argon2id { passphrase, params } => {
+                if passphrase.is_empty() {
+                    return Err(Error::InvalidInput("passphrase is empty".to_string()));
+                }
+                let kdf = load_or_create_kdf_file(db_path, *params)?;
+                let salt = from_hex(&kdf.salt)
+                    .ok_or_else(|| Error::Corrupt("salt file has an invalid salt".to_string()))?;
+                Ok(raw_key(&derive_argon2id(passphrase, &salt, kdf.params)?))
+            }
+        }
+    }
+}
+
+/// The key for a database about to be written, and the salt file to put
+/// next to it once it is.
+pub(crate) struct FreshKey {
+    pub pragma: String,
+    kdf: Option<KdfFile>,
+}
+
+impl KeySpec {
+    /// Like `pragma_value`, but an Argon2id key gets a new salt instead of
+    /// the one stored for `db_path`, and nothing is written yet.
+    pub(crate) fn fresh(&self) -> Result<FreshKey> {
+        match self {
+            KeySpec::Argon2id { passphrase, params } => {
+                if passphrase.is_empty() {
+                    return Err(Error::InvalidInput("passphrase is empty".to_string()));
+                }
+                let mut salt = [0u8; SALT_LEN];
+                getrandom::getrandom(&mut salt).map_err(|e| Error::Io(e.to_string()))?;
+                let pragma = raw_key(&derive_argon2id(passphrase, &salt, *params)?);
+                let kdf = KdfFile { version: KDF_FILE_VERSION, salt: to_hex(&salt), params: *params };
+                Ok(FreshKey { pragma, kdf: Some(kdf) })
+            }
+            // Neither reads the path
+            other => Ok(FreshKey { pragma: other.pragma_value(Path::new(""))?, kdf: None }),
+        }
+    }
+}
+
+impl FreshKey {
+    /// Writes the salt file for `db_path`, or removes a stale one when the
+    /// new key has no salt. The file is replaced by a rename, so a crash
+    /// leaves either the old salt or the new one.
+    pub(crate) fn install_salt_file(&self, db_path: &Path) -> Result<()> {
+        let path = kdf_file_path(db_path);
+        match &self.kdf {
+            Some(kdf) => {
+                let mut partial = path.clone().into_os_string();
+                partial.push(".partial");
+                std::fs::write(&partial, serde_json::to_string(kdf)?)?;
+                Ok(std::fs::rename(&partial, &path)?)
+            }
+            None => match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+impl CipherConfig {
+    /// Applies the cipher settings to a connection whose key has just been
+    /// set. SQLCipher reads them on the first page access, so nothing may
+    /// touch the database in between.
+    pub(crate) fn apply_settings(&self, conn: &Connection) -> Result<()> {
+        if let Some(version) = self.cipher_compatibility {
+            if !(1..=4).contains(&version) {
+                return Err(Error::InvalidInput(format!(
+                    "cipher_compatibility must be 1-4, got {version}"
+                )));
+            }
+            conn.pragma_update(None, "cipher_compatibility", version)?;
+        }
+        if let Some(size) = self.cipher_page_size {
+            if !(512..=65536).contains(&size) || !size.is_power_of_two() {
+                return Err(Error::InvalidInput(format!(
+                    "cipher_page_size must be a power of two in 512-65536, got {size}"
+                )));
+            }
+            conn.pragma_update(None, "cipher_page_size", size)?;
+        }
+        if let Some(iterations) = self.kdf_iter {
+            if iterations == 0 {
+                return Err(Error::InvalidInput("kdf_iter must be positive".to_string()));
+            }
+            conn.pragma_update(None, "kdf_iter", iterations)?;
+        }
+        Ok(())
+    }
+
+    fn has_settings(&self) -> bool {
+        self.cipher_page_size.is_some() || self.kdf_iter.is_some()
+            || self.cipher_compatibility.is_some()
+    }
+
+    /// Rejects settings that would be silently ignored.
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.key.is_none() && self.has_settings() {
+            return Err(Error::InvalidInput(
+                "cipher settings need an encryption key".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The settings without the key, for reopening the same file later.
+    pub(crate) fn without_key(&self) -> CipherConfig {
+        CipherConfig { key: None, ..self.clone() }
+    }
+}
+
+pub(crate) fn kdf_file_path(db_path: &Path) -> PathBuf {
+    let mut path = db_path.to_path_buf().into_os_string();
+    path.push(".kdf");
+    PathBuf::from(path)
+}
+
+fn load_or_create_kdf_file(db_path: &Path, params: Argon2Params) -> Result<KdfFile> {
+    let path = kdf_file_path(db_path);
+    match std::fs::read_to_string(&path) {
+        Ok(json) => {
+            let kdf: KdfFile = serde_json::from_str(&json)?;
+            if kdf.version != KDF_FILE_VERSION {
+                return Err(Error::Corrupt(format!(
+                    "unsupported salt file version {}", kdf.version
+                )));
+            }
+            Ok(kdf)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            // A new salt for an existing database can only derive the wrong key
+            let db_has_data = std::fs::metadata(db_path).map(|m| m.len() > 0).unwrap_or(false);
+            if db_has_data {
+                return Err(Error::Io(format!("salt file {} is missing", path.display())));
+            }
+            let mut salt = [0u8; SALT_LEN];
+            getrandom::getrandom(&mut salt).map_err(|e| Error::Io(e.to_string()))?;
+            let kdf = KdfFile { version: KDF_FILE_VERSION, salt: to_hex(&salt), params };
+            std::fs::write(&path, serde_json::to_string(&kdf)?)?;
+            Ok(kdf)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn derive_argon2id(passphrase: &str, salt: &[u8], params: Argon2Params) -> Result<[u8; KEY_LEN]> {
+    let params = Params::new(params.memory_kib, params.iterations, params.parallelism, Some(KEY_LEN))
+        .map_err(|e| Error::InvalidInput(format!("argon2 parameters: {e}")))?;
+    let mut key = [0u8; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| Error::InvalidInput(format!("argon2: {e}")))?;
+    Ok(key)
+}
+
+fn raw_key(key: &[u8]) -> String {
+    format!("x'{}'", to_hex(key))
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Cheap parameters so tests do not spend seconds in Argon2.
+    pub(crate) const TEST_PARAMS: Argon2Params =
+        Argon2Params { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+    fn fresh_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cb_test_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_raw_hex_key() {
+        let hex = "00112233445566778899aabbccddeeff00112233445566778899AABBCCDDEEFF";
+        let value = KeySpec::RawHex(hex.to_string()).pragma_value(Path::new("unused")).unwrap();
+        assert_eq!(value, format!("x'{}'", hex.to_lowercase()));
+
+        for bad in ["abcd", &hex[..63], &format!("{}zz", &hex[..62])] {
+            let err = KeySpec::RawHex(bad.to_string()).pragma_value(Path::new("unused"));
+            assert!(matches!(err, Err(Error::InvalidInput(_))), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_argon2id_salt_file() {
+        let dir = fresh_dir("argon2id_salt");
+        let db_path = dir.join("clipboard.db");
+        let spec = |passphrase: &str| KeySpec::Argon2id {
+            passphrase: passphrase.to_string(),
+            params: TEST_PARAMS,
+        };
+
+        let first = spec("hunter2").pragma_value(&db_path).unwrap();
+        assert!(first.starts_with("x'") && first.len() == 2 + 64 + 1);
+        assert!(kdf_file_path(&db_path).exists());
+        // The stored salt is reused, even when other parameters are passed
+        let changed = KeySpec::Argon2id {
+            passphrase: "hunter2".to_string(),
+            params: Argon2Params { iterations: 2, ..TEST_PARAMS },
+        };
+        assert_eq!(changed.pragma_value(&db_path).unwrap(), first);
+        assert_ne!(spec("hunter3").pragma_value(&db_path).unwrap(), first);
+
+        // Losing the salt of an existing database is an error, not a new key
+        std::fs::write(&db_path, b"not empty").unwrap();
+        std::fs::remove_file(kdf_file_path(&db_path)).unwrap();
+        assert!(matches!(spec("hunter2").pragma_value(&db_path), Err(Error::Io(_))));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fresh_key() {
+        let dir = fresh_dir("fresh_key");
+        let db_path = dir.join("clipboard.db");
+        let spec = KeySpec::Argon2id { passphrase: "hunter2".to_string(), params: TEST_PARAMS };
+        let stored = spec.pragma_value(&db_path).unwrap();
+
+        // A new salt, which only lands on disk when installed
+        let fresh = spec.fresh().unwrap();
+        assert_ne!(fresh.pragma, stored);
+        assert_eq!(spec.pragma_value(&db_path).unwrap(), stored);
+        fresh.install_salt_file(&db_path).unwrap();
+        assert_eq!(spec.pragma_value(&db_path).unwrap(), fresh.pragma);
+
+        // Other keys remove the salt file
+        let passphrase = KeySpec::Passphrase("hunter2".to_string()).fresh().unwrap();
+        assert_eq!(passphrase.pragma, "hunter2");
+        passphrase.install_salt_file(&db_path).unwrap();
+        assert!(!kdf_file_path(&db_path).exists());
+        passphrase.install_salt_file(&db_path).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_settings_need_key() {
+        let config = CipherConfig { kdf_iter: Some(1000), ..Default::default() };
+        assert!(matches!(config.validate(), Err(Error::InvalidInput(_))));
+
+        let config: CipherConfig = serde_json::from_str(
+            r#"{"key": {"RawHex": "ab"}, "cipher_page_size": 1000}"#,
+        ).unwrap();
+        config.validate().unwrap();
+        let conn = Connection::open_in_memory().unwrap();
+        assert!(matches!(config.apply_settings(&conn), Err(Error::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt;
+
+use rusqlite::ErrorCode;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors surfaced by cb-core. Every variant has a stable numeric code and a
+/// stable string name so the Swift side can branch on them across the FFI.
+#[derive(Debug)]
+pub enum Error {
+    NotInitialized,
+    LockPoisoned,
+    WrongKey,
+    Corrupt(String),
+    NotFound,
+    Busy,
+    InvalidInput(String),
+    Io(String),
+    UnsupportedSchema { found: i64, supported: i64 },
+    Serialization(String),
+    Database(rusqlite::Error),
+}
+
+impl Error {
+    pub fn code(&self) -> i32 {
+        match self {
+            Error::NotInitialized => 1,
+            Error::LockPoisoned => 2,
+            Error::WrongKey => 3,
+            Error::Corrupt(_) => 4,
+            Error::NotFound => 5,
+            Error::Busy => 6,
+            Error::InvalidInput(_) => 7,
+            Error::Io(_) => 8,
+            Error::UnsupportedSchema { .. } => 9,
+            Error::Serialization(_) => 10,
+            Error::Database(_) => 99,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Error::NotInitialized => "not_initialized",
+            Error::LockPoisoned => "lock_poisoned",
+            Error::WrongKey => "wrong_key",
+            Error::Corrupt(_) => "corrupt",
+            Error::NotFound => "not_found",
+            Error::Busy => "busy",
+            Error::InvalidInput(_) => "invalid_input",
+            Error::Io(_) => "io",
+            Error::UnsupportedSchema { .. } => "unsupported_schema",
+            Error::Serialization(_) => "serialization",
+            Error::Database(_) => "database",
+        }
+    }
+
+    /// Reinterprets "file is not a database" as a key mismatch. Used where a
+    /// key has just been applied, since SQLCipher cannot tell the two apart.
+    pub(crate) fn with_key_context(self) -> Self {
+        match self {
+            Error::Corrupt(_) => Error::WrongKey,
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotInitialized => write!(f, "Storage not initialized"),
+            Error::LockPoisoned => write!(f, "Storage lock poisoned"),
+            Error::WrongKey => write!(f, "Encryption key does not match the database"),
+            Error::Corrupt(msg) => write!(f, "Database is corrupt: {msg}"),
+            Error::NotFound => write!(f, "Entry not found"),
+            Error::Busy => write!(f, "Database is busy"),
+            Error::InvalidInput(msg) => write!(f, "Invalid input: {msg}"),
+            Error::Io(msg) => write!(f, "I/O error: {msg}"),
+            Error::UnsupportedSchema { found, supported } => write!(
+                f,
+                "Database schema version {found} is newer than supported version {supported}"
+            ),
+            Error::Serialization(msg) => write!(f, "Serialization failed: {msg}"),
+            Error::Database(e) => write!(f, "Database error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        match &e {
+            rusqlite::Error::QueryReturnedNoRows => Error::NotFound,
+            rusqlite::Error::SqliteFailure(err, _) => match err.code {
+                ErrorCode::NotADatabase | ErrorCode::DatabaseCorrupt => Error::Corrupt(e.to_string()),
+                ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked => Error::Busy,
+                ErrorCode::DiskFull
+                | ErrorCode::SystemIoFailure
+                | ErrorCode::CannotOpen
+                | ErrorCode::PermissionDenied
+                | ErrorCode::ReadOnly => Error::Io(e.to_string()),
+                _ => Error::Database(e),
+            },
+            _ => Error::Database(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Serialization(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sqlite_failures_map_to_variants() {
+        let busy = rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+            None,
+        );
+        assert!(matches!(Error::from(busy), Error::Busy));
+
+        let full = rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_FULL),
+            None,
+        );
+        assert!(matches!(Error::from(full), Error::Io(_)));
+
+        let notadb = rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_NOTADB),
+            None,
+        );
+        assert!(matches!(Error::from(notadb).with_key_context(), Error::WrongKey));
+
+        assert!(matches!(Error::from(rusqlite::Error::QueryReturnedNoRows), Error::NotFound));
+    }
+
+    #[test]
+    fn test_codes_are_unique() {
+        let errors = [
+            Error::NotInitialized,
+            Error::LockPoisoned,
+            Error::WrongKey,
+            Error::Corrupt(String::new()),
+            Error::NotFound,
+            Error::Busy,
+            Error::InvalidInput(String::new()),
+            Error::Io(String::new()),
+            Error::UnsupportedSchema { found: 0, supported: 0 },
+            Error::Serialization(String::new()),
+            Error::Database(rusqlite::Error::InvalidQuery),
+        ];
+        let mut codes: Vec<i32> = errors.iter().map(Error::code).collect();
+        let mut names: Vec<&str> = errors.iter().map(Error::name).collect();
+        codes.sort();
+        codes.dedup();
+        names.sort();
+        names.dedup();
+        assert_eq!(codes.len(), errors.len());
+        assert_eq!(names.len(), errors.len());
+    }
+}
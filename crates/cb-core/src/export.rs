@@ -0,0 +1,285 @@
+//! Portable NDJSON export of the history, for moving it between machines or
+//! inspecting it with `jq`.
+//!
+//! The first line is an `ExportHeader`; each following line is one
+//! `ExportedEntry`, oldest first. Images are either inline as base64 or
+//! written next to the export as `<blob_hash>.<png|jpeg|tiff|bin>` and
+//! referenced by file name. Expired entries are left out.
+//!
+//! `Storage::import_history` merges an export into a database. Entries are
+//! matched by content (`content_hash`), so importing the same file twice
+//! changes nothing. For a match:
+//!
+//! - `copy_count` is the larger of the two, not the sum, keeping repeated
+//!   imports idempotent
+//! - `first_copied_at` is the earlier and `created_at` the later
+//! - pins, tags and the absence of an expiry win over their opposites
+//!
+//! Imported text is stored as exported: it already passed the sensitive
+//! rules and the ignore list when it was copied. History from other
+//! managers did not, so `Storage::import_from` applies both.
+
+use std::io::Write;
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::models::{ClipboardEntry, ContentType};
+
+pub const FORMAT: &str = "cb-history";
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageEncoding {
+    Inline,
+    Files,
+}
+
+/// Where `Storage::export_history` puts image bytes.
+#[derive(Debug, Clone, Copy)]
+pub enum ImageExport<'a> {
+    Inline,
+    /// Side files in this directory, which is created if needed.
+    Files(&'a Path),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportHeader {
+    /// Always `FORMAT`.
+    pub format: String,
+    /// Bumped on incompatible changes; readers reject newer versions.
+    pub version: u32,
+    pub exported_at: i64,
+    /// `user_version` of the exporting database, for diagnostics.
+    pub schema_version: i64,
+    pub images: ImageEncoding,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedEntry {
+    pub content_type: ContentType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_base64: Option<String>,
+    /// File name in the image directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_file: Option<String>,
+    #[serde(default)]
+    pub source_app: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_bundle_id: Option<String>,
+    pub created_at: i64,
+    pub first_copied_at: i64,
+    pub copy_count: i64,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub pin_order: Option<i64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub sensitive: Vec<String>,
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ExportReport {
+    pub entries: u64,
+    pub images: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ImportReport {
+    /// Entries with no match in the database.
+    pub inserted: u64,
+    /// Entries merged into an existing entry with the same content.
+    pub merged: u64,
+    /// Entries whose `expires_at` had passed, or items from another
+    /// manager with nothing we can store.
+    pub skipped: u64,
+    /// Items from another manager imported with a file's mtime as their
+    /// copy time, because the source keeps no time we can read.
+    pub estimated_times: u64,
+    /// Items from another manager imported with neither a source app nor
+    /// a bundle id.
+    pub unknown_sources: u64,
+}
+
+impl ExportHeader {
+    pub(crate) fn new(exported_at: i64, schema_version: i64, images: &ImageExport) -> Self {
+        ExportHeader {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            exported_at,
+            schema_version,
+            images: match images {
+                ImageExport::Inline => ImageEncoding::Inline,
+                ImageExport::Files(_) => ImageEncoding::Files,
+            },
+        }
+    }
+
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.format != FORMAT {
+            return Err(Error::InvalidInput(format!("not a {FORMAT} export: {}", self.format)));
+        }
+        if self.version > FORMAT_VERSION {
+            return Err(Error::InvalidInput(format!(
+                "export version {} is newer than supported version {FORMAT_VERSION}",
+                self.version
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl ExportedEntry {
+    /// Converts a stored entry, writing its image to the image directory
+    /// when images go to side files. `image_format` is the thumbnail's
+    /// format name, if the image could be decoded.
+    pub(crate) fn new(
+        entry: ClipboardEntry,
+        image: Option<&[u8]>,
+        image_format: Option<&str>,
+        images: &ImageExport,
+    ) -> Result<Self> {
+        let (image_base64, image_file) = match (image, images) {
+            (None, _) => (None, None),
+            (Some(data), ImageExport::Inline) => (Some(BASE64.encode(data)), None),
+            (Some(data), ImageExport::Files(dir)) => {
+                let hash = entry.blob_hash.clone().unwrap_or_else(|| crate::blobs::hash(data));
+                let name = format!("{hash}.{}", image_format.unwrap_or("bin"));
+                std::fs::create_dir_all(dir)?;
+                std::fs::write(dir.join(&name), data)?;
+                (None, Some(name))
+            }
+        };
+        Ok(ExportedEntry {
+            content_type: entry.content_type,
+            text: entry.text_content,
+            image_base64,
+            image_file,
+            source_app: entry.source_app,
+            source_bundle_id: entry.source_bundle_id,
+            created_at: entry.created_at,
+            first_copied_at: entry.first_copied_at,
+            copy_count: entry.copy_count,
+            pinned: entry.pinned,
+            pin_order: entry.pin_order,
+            tags: entry.tags,
+            sensitive: entry.sensitive,
+            expires_at: entry.expires_at,
+        })
+    }
+
+    /// The image bytes, decoded or read from `image_dir`.
+    pub(crate) fn image(&self, image_dir: Option<&Path>) -> Result<Option<Vec<u8>>> {
+        if let Some(encoded) = &self.image_base64 {
+            return BASE64.decode(encoded)
+                .map(Some)
+                .map_err(|e| Error::InvalidInput(format!("image_base64: {e}")));
+        }
+        let Some(name) = &self.image_file else {
+            return Ok(None);
+        };
+        // A bare file name, so an export cannot read files elsewhere
+        if Path::new(name).file_name().and_then(|n| n.to_str()) != Some(name.as_str()) {
+            return Err(Error::InvalidInput(format!("image_file is not a file name: {name}")));
+        }
+        let Some(dir) = image_dir else {
+            return Err(Error::InvalidInput(format!("image_file {name} needs an image directory")));
+        };
+        Ok(Some(std::fs::read(dir.join(name))?))
+    }
+}
+
+pub(crate) fn write_line<T: Serialize>(out: &mut impl Write, value: &T) -> Result<()> {
+    serde_json::to_writer(&mut *out, value)?;
+    out.write_all(b"\n")?;
+    Ok(())
+}
+
+/// Parses line `number` (1-based) of an export.
+pub(crate) fn parse_line<T: for<'de> Deserialize<'de>>(number: usize, line: &str) -> Result<T> {
+    serde_json::from_str(line).map_err(|e| Error::InvalidInput(format!("line {number}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(content_type: ContentType) -> ClipboardEntry {
+        ClipboardEntry {
+            id: 1,
+            content_type,
+            text_content: None,
+            image_data: None,
+            source_app: Some("Preview".to_string()),
+            source_bundle_id: None,
+            created_at: 2,
+            copy_count: 1,
+            first_copied_at: 2,
+            pinned: false,
+            pin_order: None,
+            tags: vec![],
+            image_width: None,
+            image_height: None,
+            blob_hash: Some("abc".to_string()),
+            sensitive: vec![],
+            expires_at: None,
+        }
+    }
+
+    #[test]
+    fn test_header_versions() {
+        let header = ExportHeader::new(0, 14, &ImageExport::Inline);
+        header.check().unwrap();
+        let json = serde_json::to_string(&header).unwrap();
+        assert!(json.contains(r#""format":"cb-history","version":1"#));
+        assert!(json.contains(r#""images":"inline""#));
+
+        let newer = ExportHeader { version: FORMAT_VERSION + 1, ..header.clone() };
+        assert!(matches!(newer.check(), Err(Error::InvalidInput(_))));
+        let other = ExportHeader { format: "maccy".to_string(), ..header };
+        assert!(matches!(other.check(), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_image_side_files() {
+        let dir = std::env::temp_dir().join("cb_test_export_images");
+        let _ = std::fs::remove_dir_all(&dir);
+        let exported = ExportedEntry::new(
+            entry(ContentType::Image),
+            Some(b"pixels"),
+            Some("png"),
+            &ImageExport::Files(&dir),
+        ).unwrap();
+        assert_eq!(exported.image_file.as_deref(), Some("abc.png"));
+        assert_eq!(exported.image(Some(&dir)).unwrap().unwrap(), b"pixels");
+        assert!(matches!(exported.image(None), Err(Error::InvalidInput(_))));
+
+        let escaping = ExportedEntry { image_file: Some("../abc.png".to_string()), ..exported };
+        assert!(matches!(escaping.image(Some(&dir)), Err(Error::InvalidInput(_))));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_inline_images() {
+        let exported = ExportedEntry::new(entry(ContentType::Image), Some(&[0, 255]), None, &ImageExport::Inline)
+            .unwrap();
+        assert_eq!(exported.image_base64.as_deref(), Some("AP8="));
+        assert_eq!(exported.image(None).unwrap().unwrap(), vec![0, 255]);
+
+        let line = serde_json::to_string(&exported).unwrap();
+        assert!(!line.contains("image_file") && !line.contains("\"text\""));
+        let bad: ExportedEntry = parse_line(2, &line.replace("AP8=", "!!")).unwrap();
+        assert!(matches!(bad.image(None), Err(Error::InvalidInput(_))));
+        assert!(matches!(parse_line::<ExportedEntry>(3, "{"), Err(Error::InvalidInput(m)) if m.starts_with("line 3")));
+    }
+}
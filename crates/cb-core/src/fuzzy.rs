@@ -0,0 +1,246 @@
+//! Typo-tolerant matching for `Storage::search_entries_fuzzy`, used when
+//! full-text search returns too few hits (e.g. `kubectl get podz`).
+//!
+//! Each query word must match some word of the entry, trying in order:
+//! exact, prefix, substring, up to one or two typos (optimal string
+//! alignment distance against a prefix of the word), and an fzf-style
+//! subsequence anchored at the start of the word. The entry score is the
+//! sum of the per-word scores.
+
+use std::cmp::Reverse;
+
+use crate::models::FuzzyMatch;
+
+const SCORE_EXACT: i64 = 100;
+const SCORE_PREFIX: i64 = 90;
+const SCORE_SUBSTRING: i64 = 70;
+const SCORE_TYPO: i64 = 60;
+const TYPO_PENALTY: i64 = 10;
+const SCORE_SUBSEQUENCE: i64 = 40;
+const SCORE_SUBSEQUENCE_MIN: i64 = 10;
+
+/// Only the start of each entry is scanned, so one huge paste cannot
+/// dominate the scan budget.
+const MAX_SCAN_CHARS: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzyOptions {
+    /// Fuzzy matching runs when full-text search returns fewer hits than this.
+    pub min_hits: usize,
+    /// Maximum number of recent text entries scored per call.
+    pub scan_budget: usize,
+}
+
+impl Default for FuzzyOptions {
+    fn default() -> Self {
+        FuzzyOptions { min_hits: 3, scan_budget: 2000 }
+    }
+}
+
+/// A word of the scanned text: lowercased chars and the UTF-16 offset of each.
+struct Word {
+    chars: Vec<char>,
+    offsets: Vec<usize>,
+}
+
+fn split_words(text: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut current = Word { chars: Vec::new(), offsets: Vec::new() };
+    let mut offset = 0;
+    for c in text.chars().take(MAX_SCAN_CHARS) {
+        if c.is_alphanumeric() {
+            current.chars.push(fold_case(c));
+            current.offsets.push(offset);
+        } else if !current.chars.is_empty() {
+            words.push(std::mem::replace(&mut current, Word { chars: Vec::new(), offsets: Vec::new() }));
+        }
+        offset += c.len_utf16();
+    }
+    if !current.chars.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn fold_case(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn max_typos(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// A parsed fuzzy query.
+pub struct Pattern {
+    words: Vec<Vec<char>>,
+}
+
+impl Pattern {
+    /// Returns `None` when the query has no alphanumeric words.
+    pub fn new(query: &str) -> Option<Self> {
+        let words: Vec<Vec<char>> = split_words(query).into_iter().map(|w| w.chars).collect();
+        if words.is_empty() { None } else { Some(Pattern { words }) }
+    }
+
+    /// Scores `text`; `None` unless every query word matches. Positions are
+    /// UTF-16 offsets into `text`, ascending.
+    pub fn match_text(&self, text: &str) -> Option<FuzzyMatch> {
+        let words = split_words(text);
+        let mut score = 0;
+        let mut positions = Vec::new();
+        for query_word in &self.words {
+            let (word_score, word, indices) = words.iter()
+                .filter_map(|word| match_word(query_word, &word.chars).map(|(s, idx)| (s, word, idx)))
+                .max_by_key(|(s, ..)| *s)?;
+            score += word_score;
+            positions.extend(indices.into_iter().map(|i| word.offsets[i]));
+        }
+        positions.sort_unstable();
+        positions.dedup();
+        Some(FuzzyMatch { score, positions })
+    }
+}
+
+/// Best match of one query word against one text word, with the matched
+/// indices into `word`.
+fn match_word(query: &[char], word: &[char]) -> Option<(i64, Vec<usize>)> {
+    if query == word {
+        return Some((SCORE_EXACT, (0..word.len()).collect()));
+    }
+    if word.starts_with(query) {
+        return Some((SCORE_PREFIX, (0..query.len()).collect()));
+    }
+    if let Some(start) = word.windows(query.len()).position(|w| w == query) {
+        return Some((SCORE_SUBSTRING, (start..start + query.len()).collect()));
+    }
+    if let Some((distance, indices)) = typo_match(query, word, max_typos(query.len())) {
+        return Some((SCORE_TYPO - TYPO_PENALTY * distance as i64, indices));
+    }
+    subsequence_match(query, word).map(|indices| {
+        let span = indices[indices.len() - 1] - indices[0] + 1;
+        let gaps = (span - query.len()) as i64;
+        ((SCORE_SUBSEQUENCE - gaps).max(SCORE_SUBSEQUENCE_MIN), indices)
+    })
+}
+
+/// Optimal string alignment distance between `query` and the closest prefix
+/// of `word`, with the indices of `word` aligned to an equal query char.
+fn typo_match(query: &[char], word: &[char], max_typos: usize) -> Option<(usize, Vec<usize>)> {
+    if max_typos == 0 {
+        return None;
+    }
+    let word = &word[..word.len().min(query.len() + max_typos)];
+    let (n, m) = (query.len(), word.len());
+    let transposed = |i: usize, j: usize| {
+        i > 1 && j > 1 && query[i - 1] == word[j - 2] && query[i - 2] == word[j - 1]
+    };
+
+    let mut d = vec![vec![0usize; m + 1]; n + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let cost = usize::from(query[i - 1] != word[j - 1]);
+            let mut value = (d[i - 1][j] + 1).min(d[i][j - 1] + 1).min(d[i - 1][j - 1] + cost);
+            if transposed(i, j) {
+                value = value.min(d[i - 2][j - 2] + 1);
+            }
+            d[i][j] = value;
+        }
+    }
+
+    // Prefer the longest prefix among equally close ones
+    let (end, distance) = (0..=m).map(|j| (j, d[n][j])).min_by_key(|&(j, dist)| (dist, Reverse(j)))?;
+    if distance > max_typos {
+        return None;
+    }
+
+    let mut indices = Vec::new();
+    let (mut i, mut j) = (n, end);
+    while i > 0 && j > 0 {
+        if query[i - 1] == word[j - 1] && d[i][j] == d[i - 1][j - 1] {
+            indices.push(j - 1);
+            i -= 1;
+            j -= 1;
+        } else if transposed(i, j) && d[i][j] == d[i - 2][j - 2] + 1 {
+            indices.extend([j - 1, j - 2]);
+            i -= 2;
+            j -= 2;
+        } else if d[i][j] == d[i - 1][j - 1] + 1 {
+            i -= 1;
+            j -= 1;
+        } else if d[i][j] == d[i - 1][j] + 1 {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    indices.reverse();
+    Some((distance, indices))
+}
+
+/// Query chars in order within `word`, starting at its first char.
+fn subsequence_match(query: &[char], word: &[char]) -> Option<Vec<usize>> {
+    if query.len() < 2 || word.first() != query.first() {
+        return None;
+    }
+    let mut indices = Vec::with_capacity(query.len());
+    let mut chars = word.iter().enumerate();
+    for q in query {
+        let (index, _) = chars.find(|(_, c)| *c == q)?;
+        indices.push(index);
+    }
+    Some(indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(query: &str, text: &str) -> Option<i64> {
+        Pattern::new(query).unwrap().match_text(text).map(|m| m.score)
+    }
+
+    #[test]
+    fn test_match_tiers() {
+        assert_eq!(score("pods", "get pods"), Some(SCORE_EXACT));
+        assert_eq!(score("pod", "get pods"), Some(SCORE_PREFIX));
+        assert_eq!(score("ods", "get pods"), Some(SCORE_SUBSTRING));
+        assert_eq!(score("podz", "get pods"), Some(SCORE_TYPO - TYPO_PENALTY));
+        assert_eq!(score("kubetcl", "kubectl"), Some(SCORE_TYPO - TYPO_PENALTY));
+        assert_eq!(score("kbctl", "kubectl"), Some(SCORE_SUBSEQUENCE - 2));
+        assert_eq!(score("Kubectl GET", "kubectl get pods"), Some(2 * SCORE_EXACT));
+    }
+
+    #[test]
+    fn test_every_word_must_match() {
+        assert_eq!(score("kubectl get podz", "kubectl get pods -n default"), Some(250));
+        assert_eq!(score("kubectl deploy", "kubectl get pods"), None);
+        // Short words get no typo allowance
+        assert_eq!(score("gte", "get"), None);
+        assert_eq!(score("xyz", "abc"), None);
+    }
+
+    #[test]
+    fn test_positions_are_utf16_offsets() {
+        let m = Pattern::new("podz").unwrap().match_text("😀 get pods").unwrap();
+        // "😀" takes two UTF-16 code units; "pods" starts at 7
+        assert_eq!(m.positions, vec![7, 8, 9]);
+
+        let m = Pattern::new("kubetcl").unwrap().match_text("kubectl").unwrap();
+        assert_eq!(m.positions, vec![0, 1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_pattern_without_words() {
+        assert!(Pattern::new("  -- ").is_none());
+    }
+}
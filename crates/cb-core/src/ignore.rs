@@ -0,0 +1,143 @@
+//! Source apps whose copies are never recorded.
+//!
+//! Rules live in the `ignored_apps` table and are checked by the FFI save
+//! functions before anything is stored. All comparisons ignore case:
+//!
+//! - `Name` equals the app's localized name, e.g. `1Password 7`
+//! - `BundleId` equals the app's bundle identifier, e.g. `com.agilebits.onepassword7`
+//! - `Glob` matches either of them with `*` (any run) and `?` (one char),
+//!   e.g. `com.agilebits.*` or `*Bank*`
+//!
+//! Entries only record the app name, so `Storage::purge_ignored_app_entries`
+//! cannot find past copies for a `BundleId` rule.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IgnoreKind {
+    Name,
+    BundleId,
+    Glob,
+}
+
+impl IgnoreKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            IgnoreKind::Name => "Name",
+            IgnoreKind::BundleId => "BundleId",
+            IgnoreKind::Glob => "Glob",
+        }
+    }
+
+    /// Unlike the other enums crossing the FFI, an unknown kind is an error:
+    /// guessing could ignore the wrong apps.
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "Name" => Ok(IgnoreKind::Name),
+            "BundleId" => Ok(IgnoreKind::BundleId),
+            "Glob" => Ok(IgnoreKind::Glob),
+            _ => Err(Error::InvalidInput(format!("unknown ignore kind: {s}"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IgnoredApp {
+    pub id: i64,
+    pub kind: IgnoreKind,
+    pub pattern: String,
+    pub created_at: i64,
+}
+
+impl IgnoredApp {
+    /// Whether a copy from `app` (localized name) and `bundle_id` is ignored.
+    /// Empty values never match.
+    pub fn matches(&self, app: &str, bundle_id: &str) -> bool {
+        let pattern = self.pattern.to_lowercase();
+        let candidates: &[&str] = match self.kind {
+            IgnoreKind::Name => &[app],
+            IgnoreKind::BundleId => &[bundle_id],
+            IgnoreKind::Glob => &[app, bundle_id],
+        };
+        candidates.iter()
+            .filter(|value| !value.is_empty())
+            .map(|value| value.to_lowercase())
+            .any(|value| match self.kind {
+                IgnoreKind::Glob => glob_match(&pattern, &value),
+                _ => value == pattern,
+            })
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it was tried at
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(kind: IgnoreKind, pattern: &str) -> IgnoredApp {
+        IgnoredApp { id: 1, kind, pattern: pattern.to_string(), created_at: 0 }
+    }
+
+    #[test]
+    fn test_match_kinds() {
+        let name = rule(IgnoreKind::Name, "1password 7");
+        assert!(name.matches("1Password 7", ""));
+        assert!(!name.matches("1Password", "1password 7"));
+
+        let bundle = rule(IgnoreKind::BundleId, "com.agilebits.onepassword7");
+        assert!(bundle.matches("", "com.agilebits.onepassword7"));
+        assert!(!bundle.matches("com.agilebits.onepassword7", ""));
+
+        let glob = rule(IgnoreKind::Glob, "com.agilebits.*");
+        assert!(glob.matches("1Password", "com.agilebits.onepassword7"));
+        assert!(!glob.matches("com.agilebits", ""));
+        assert!(rule(IgnoreKind::Glob, "*bank*").matches("My Bank App", ""));
+        assert!(!rule(IgnoreKind::Glob, "*").matches("", ""));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(glob_match("a*c", "abcbc"));
+        assert!(glob_match("?ank", "bank"));
+        assert!(glob_match("**", ""));
+        assert!(!glob_match("a*b", "acbc"));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn test_parse_kind() {
+        assert_eq!(IgnoreKind::parse("BundleId").unwrap(), IgnoreKind::BundleId);
+        assert!(matches!(IgnoreKind::parse("bundle"), Err(Error::InvalidInput(_))));
+    }
+}
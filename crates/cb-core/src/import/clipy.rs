@@ -0,0 +1,117 @@
+//! Clipy's clip archives, `~/Library/Application Support/Clipy/*.data`.
+//! Each is a `CPYClipData` written by `NSKeyedArchiver`. The copy times,
+//! source apps and ordering live in Clipy's Realm database, which has no
+//! open reader, so the file's mtime stands in for the copy time. Images are
+//! archived `NSImage` graphs and are skipped.
+
+use std::path::Path;
+
+use plist::{Dictionary, Value};
+
+use super::{Copies, Pasteboard, Scan, modified_millis};
+use crate::error::{Error, Result};
+
+/// The object table of a keyed archive, resolving `UID` references.
+struct Archive {
+    objects: Vec<Value>,
+}
+
+impl Archive {
+    fn resolve<'a>(&'a self, value: &'a Value) -> Option<&'a Value> {
+        match value {
+            // Index 0 is the `$null` placeholder
+            Value::Uid(uid) if uid.get() == 0 => None,
+            Value::Uid(uid) => self.objects.get(uid.get() as usize),
+            other => Some(other),
+        }
+    }
+
+    /// `NSString` is stored inline; `NSMutableString` as `NS.string`.
+    fn string(&self, value: Option<&Value>) -> Option<String> {
+        match self.resolve(value?)? {
+            Value::String(s) => Some(s.clone()),
+            Value::Dictionary(dict) => self.string(dict.get("NS.string")),
+            _ => None,
+        }
+    }
+
+    fn strings(&self, value: Option<&Value>) -> Vec<String> {
+        let objects = value
+            .and_then(|value| self.resolve(value))
+            .and_then(Value::as_dictionary)
+            .and_then(|dict| dict.get("NS.objects"))
+            .and_then(Value::as_array);
+        objects.into_iter().flatten().filter_map(|object| self.string(Some(object))).collect()
+    }
+}
+
+/// The archive's object table and root object.
+fn open(path: &Path) -> Result<(Archive, Dictionary)> {
+    let invalid = || Error::InvalidInput(format!("{} is not a keyed archive", path.display()));
+    let value = Value::from_file(path).map_err(|_| invalid())?;
+    let mut top = value.into_dictionary().ok_or_else(invalid)?;
+    let objects = top.remove("$objects").and_then(Value::into_array).ok_or_else(invalid)?;
+    let root = top.get("$top")
+        .and_then(Value::as_dictionary)
+        .and_then(|t| t.get("root"))
+        .and_then(Value::as_uid)
+        .and_then(|uid| objects.get(uid.get() as usize))
+        .and_then(Value::as_dictionary)
+        .cloned()
+        .ok_or_else(invalid)?;
+    Ok((Archive { objects }, root))
+}
+
+pub(super) fn read(path: &Path) -> Result<Scan> {
+    let mut files = Vec::new();
+    for dir_entry in std::fs::read_dir(path)? {
+        let file = dir_entry?.path();
+        if file.extension().is_some_and(|ext| ext == "data") {
+            files.push((modified_millis(&file)?, file));
+        }
+    }
+    files.sort();
+
+    let mut scan = Scan { estimated_times: true, ..Default::default() };
+    for (modified, file) in files {
+        let Ok((archive, root)) = open(&file) else {
+            scan.skipped += 1;
+            continue;
+        };
+        let pasteboard = Pasteboard {
+            text: archive.string(root.get("stringValue")),
+            file_paths: archive.strings(root.get("fileNames")),
+            image: None,
+        };
+        let copies = Copies { first_copied_at: modified, last_copied_at: modified, count: 1 };
+        match pasteboard.into_item(None, copies, false) {
+            Some(item) => scan.items.push(item),
+            None => scan.skipped += 1,
+        }
+    }
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::tests::fixture;
+    use crate::models::ContentType;
+
+    #[test]
+    fn test_read_fixture() {
+        let scan = read(&fixture("clipy")).unwrap();
+        // The image-only clip and the file that is not an archive
+        assert_eq!(scan.skipped, 2);
+        assert!(scan.estimated_times);
+        let mut texts: Vec<_> = scan.items.iter()
+            .map(|(entry, _)| (entry.content_type.clone(), entry.text.clone().unwrap()))
+            .collect();
+        texts.sort_by(|a, b| a.1.cmp(&b.1));
+        assert_eq!(texts, vec![
+            (ContentType::FilePath, "/Users/me/report.pdf".to_string()),
+            (ContentType::PlainText, "hello from clipy".to_string()),
+        ]);
+        assert!(scan.items.iter().all(|(entry, _)| entry.created_at > 0 && entry.source_app.is_none() && entry.source_bundle_id.is_none()));
+    }
+}
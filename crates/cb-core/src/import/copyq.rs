@@ -0,0 +1,125 @@
+//! A CopyQ tab synchronized to a folder by its Synchronize plugin. Each item
+//! is a set of files sharing a base name, one per format, e.g.
+//! `copyq_0001.txt` and `copyq_0001.html`. Hidden files (CopyQ's `.copyq_s`
+//! index) are ignored, and the newest mtime of an item's files stands in for
+//! the copy time. The binary `copyq_tab_*.dat` files of unsynchronized tabs
+//! are a private Qt serialization; a path holding them is rejected rather
+//! than read as an empty tab.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::{Copies, Pasteboard, Scan, file_url_path, modified_millis};
+use crate::error::{Error, Result};
+
+/// Whether `name` is one of the binary tab files in CopyQ's config folder.
+fn is_tab_data(name: &str) -> bool {
+    name.starts_with("copyq_tab_") && name.ends_with(".dat")
+}
+
+pub(super) fn read(path: &Path) -> Result<Scan> {
+    let tab_data = || Error::InvalidInput(format!(
+        "{} holds CopyQ's binary tab data, which cannot be read; enable the Synchronize plugin for the tab and import its folder",
+        path.display()
+    ));
+    if path.file_name().and_then(|name| name.to_str()).is_some_and(is_tab_data) {
+        return Err(tab_data());
+    }
+    if !path.is_dir() {
+        return Err(Error::InvalidInput(format!(
+            "{} is not a folder; enable CopyQ's Synchronize plugin for the tab and import its folder",
+            path.display()
+        )));
+    }
+    // Files of each item keyed by base name, then by lowercase extension
+    let mut groups: BTreeMap<String, BTreeMap<String, PathBuf>> = BTreeMap::new();
+    for dir_entry in std::fs::read_dir(path)? {
+        let file = dir_entry?.path();
+        let Some(name) = file.file_name().and_then(|name| name.to_str()) else { continue };
+        if name.starts_with('.') || !file.is_file() {
+            continue;
+        }
+        if is_tab_data(name) {
+            return Err(tab_data());
+        }
+        let (base, extension) = name.split_once('.').unwrap_or((name, ""));
+        groups.entry(base.to_string()).or_default().insert(extension.to_lowercase(), file.clone());
+    }
+
+    let mut items = Vec::new();
+    let mut skipped = 0;
+    for files in groups.into_values() {
+        let mut modified = 0;
+        for file in files.values() {
+            modified = modified.max(modified_millis(file)?);
+        }
+        let mut pasteboard = Pasteboard::default();
+        for (extension, file) in &files {
+            match extension.as_str() {
+                "txt" => pasteboard.text = Some(String::from_utf8_lossy(&std::fs::read(file)?).into_owned()),
+                "uri" => {
+                    let list = std::fs::read_to_string(file)?;
+                    pasteboard.file_paths.extend(list.lines().filter_map(file_url_path));
+                }
+                "png" | "jpg" | "jpeg" | "tif" | "tiff" if pasteboard.image.is_none() => {
+                    pasteboard.image = Some(std::fs::read(file)?);
+                }
+                _ => {}
+            }
+        }
+        let copies = Copies { first_copied_at: modified, last_copied_at: modified, count: 1 };
+        match pasteboard.into_item(None, copies, false) {
+            Some(item) => items.push((modified, item)),
+            None => skipped += 1,
+        }
+    }
+    items.sort_by_key(|(modified, _)| *modified);
+    Ok(Scan { items: items.into_iter().map(|(_, item)| item).collect(), skipped, estimated_times: true })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::tests::fixture;
+    use crate::models::ContentType;
+
+    #[test]
+    fn test_read_fixture() {
+        let scan = read(&fixture("copyq")).unwrap();
+        // copyq_0003 only has an SVG
+        assert_eq!(scan.skipped, 1);
+        assert!(scan.estimated_times);
+        let mut found: Vec<_> = scan.items.iter()
+            .map(|(entry, image)| (entry.content_type.clone(), entry.text.clone(), image.is_some()))
+            .collect();
+        found.sort_by_key(|(_, text, _)| text.clone());
+        assert_eq!(found, vec![
+            (ContentType::Image, None, true),
+            (ContentType::FilePath, Some("/home/me/notes.txt".to_string()), false),
+            (ContentType::PlainText, Some("<b>bold</b> as text".to_string()), false),
+            (ContentType::PlainText, Some("hello from copyq\n".to_string()), false),
+        ]);
+    }
+
+    #[test]
+    fn test_rejects_tab_files() {
+        let result = read(&fixture("copyq/copyq_0000.txt"));
+        assert!(matches!(result, Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_rejects_binary_tabs() {
+        let dir = std::env::temp_dir().join("cb_test_copyq_config");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("copyq.conf"), "[Options]\n").unwrap();
+        let tab = dir.join("copyq_tab_JmNsaXBib2FyZA==.dat");
+        std::fs::write(&tab, [0u8; 16]).unwrap();
+
+        for path in [&dir, &tab] {
+            let result = read(path);
+            assert!(matches!(result, Err(Error::InvalidInput(m)) if m.contains("binary tab data")));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+//! Reads the history of other clipboard managers so people switching to us
+//! keep it. `Storage::import_from` merges the result with the same rules as
+//! `Storage::import_history` (see `export`).
+//!
+//! | Source | Path | Read |
+//! |--------|------|------|
+//! | `Maccy` | `Storage.sqlite` in Maccy's container | text, file URLs and images with timestamps, copy counts, pins and source bundle ids |
+//! | `Clipy` | the `Clipy` folder of `*.data` archives | text and file names; time from the file's mtime |
+//! | `CopyQ` | a tab folder of the Synchronize plugin | `.txt`, `.uri` and image files; time from the mtime |
+//!
+//! Paste is not supported: its history is an undocumented Core Data model.
+//! Maccy records only bundle ids, so its items have a `source_bundle_id`
+//! and no `source_app`; Clipy and CopyQ record neither. `ImportReport`
+//! counts the items imported without a source or with a time taken from an
+//! mtime.
+//!
+//! Items with no text, file or image (e.g. only PDF or a color) are counted
+//! as skipped rather than failing the import. Readers return text as it
+//! was copied; the ignore list and sensitive rules are applied when
+//! merging.
+
+mod clipy;
+mod copyq;
+mod maccy;
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::export::ExportedEntry;
+use crate::models::ContentType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportSource {
+    Maccy,
+    Clipy,
+    CopyQ,
+}
+
+impl ImportSource {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ImportSource::Maccy => "Maccy",
+            ImportSource::Clipy => "Clipy",
+            ImportSource::CopyQ => "CopyQ",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "Maccy" => Ok(ImportSource::Maccy),
+            "Clipy" => Ok(ImportSource::Clipy),
+            "Paste" => Err(Error::InvalidInput(
+                "Paste keeps its history in an undocumented Core Data model and cannot be imported".to_string(),
+            )),
+            "CopyQ" => Ok(ImportSource::CopyQ),
+            _ => Err(Error::InvalidInput(format!("unknown import source: {s}"))),
+        }
+    }
+}
+
+/// Entries read from another manager, with image bytes alongside.
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub items: Vec<(ExportedEntry, Option<Vec<u8>>)>,
+    /// Items with nothing we can store.
+    pub skipped: u64,
+    /// Whether copy times come from file mtimes rather than the source.
+    pub estimated_times: bool,
+}
+
+pub fn read(source: ImportSource, path: &Path) -> Result<Scan> {
+    match source {
+        ImportSource::Maccy => maccy::read(path),
+        ImportSource::Clipy => clipy::read(path),
+        ImportSource::CopyQ => copyq::read(path),
+    }
+}
+
+/// What one copied item held, before choosing how to store it.
+#[derive(Debug, Default)]
+struct Pasteboard {
+    text: Option<String>,
+    file_paths: Vec<String>,
+    image: Option<Vec<u8>>,
+}
+
+/// When and how often an item was copied, in milliseconds.
+#[derive(Debug, Clone, Copy)]
+struct Copies {
+    first_copied_at: i64,
+    last_copied_at: i64,
+    count: i64,
+}
+
+impl Pasteboard {
+    /// Mirrors `ClipboardMonitor`: file URLs become a `FilePath` when there
+    /// is exactly one, then text wins over images.
+    fn into_item(
+        self,
+        source_bundle_id: Option<String>,
+        copies: Copies,
+        pinned: bool,
+    ) -> Option<(ExportedEntry, Option<Vec<u8>>)> {
+        let (content_type, text, image) = match self {
+            Pasteboard { file_paths, .. } if file_paths.len() == 1 => {
+                (ContentType::FilePath, file_paths.into_iter().next(), None)
+            }
+            Pasteboard { file_paths, text: None, .. } if !file_paths.is_empty() => {
+                (ContentType::PlainText, Some(file_paths.join("\n")), None)
+            }
+            Pasteboard { text: Some(text), .. } if !text.is_empty() => (ContentType::PlainText, Some(text), None),
+            Pasteboard { image: Some(image), .. } if !image.is_empty() => (ContentType::Image, None, Some(image)),
+            _ => return None,
+        };
+        let entry = ExportedEntry {
+            content_type,
+            text,
+            image_base64: None,
+            image_file: None,
+            source_app: None,
+            source_bundle_id,
+            created_at: copies.last_copied_at,
+            first_copied_at: copies.first_copied_at,
+            copy_count: copies.count.max(1),
+            pinned,
+            pin_order: None,
+            tags: vec![],
+            sensitive: vec![],
+            expires_at: None,
+        };
+        Some((entry, image))
+    }
+}
+
+/// A file's modification time in milliseconds, for formats without
+/// timestamps of their own.
+fn modified_millis(path: &Path) -> Result<i64> {
+    let modified = std::fs::metadata(path)?.modified()?;
+    Ok(modified.duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_millis() as i64))
+}
+
+/// Path of a `file://` URL, percent-decoded. Other schemes give `None`.
+fn file_url_path(url: &str) -> Option<String> {
+    let rest = url.trim().strip_prefix("file://")?;
+    // Skip the host part, normally empty or `localhost`
+    let path = &rest[rest.find('/')?..];
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut iter = path.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    let path = String::from_utf8(bytes).ok()?;
+    // Folders keep a trailing slash in URLs but not in paths
+    Some(match path.strip_suffix('/') {
+        Some(trimmed) if !trimmed.is_empty() => trimmed.to_string(),
+        _ => path,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    pub(crate) fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/import").join(name)
+    }
+
+    #[test]
+    fn test_file_url_path() {
+        assert_eq!(file_url_path("file:///Users/me/My%20Notes.txt").as_deref(), Some("/Users/me/My Notes.txt"));
+        assert_eq!(file_url_path("file://localhost/tmp/").as_deref(), Some("/tmp"));
+        assert_eq!(file_url_path("file:///").as_deref(), Some("/"));
+        assert_eq!(file_url_path("https://example.com/a"), None);
+        assert_eq!(file_url_path("file:///bad%zz"), None);
+    }
+
+    #[test]
+    fn test_content_precedence() {
+        let copies = Copies { first_copied_at: 1, last_copied_at: 2, count: 0 };
+        let both = Pasteboard { text: Some("a".into()), image: Some(vec![1]), ..Default::default() };
+        let (entry, image) = both.into_item(None, copies, false).unwrap();
+        assert_eq!((entry.content_type, entry.copy_count, image), (ContentType::PlainText, 1, None));
+
+        let files = Pasteboard { file_paths: vec!["/a".into(), "/b".into()], ..Default::default() };
+        let (entry, _) = files.into_item(None, copies, false).unwrap();
+        assert_eq!((entry.content_type, entry.text.as_deref()), (ContentType::PlainText, Some("/a\n/b")));
+
+        let empty = Pasteboard { text: Some(String::new()), ..Default::default() };
+        assert!(empty.into_item(None, copies, false).is_none());
+    }
+
+    #[test]
+    fn test_paste_is_unsupported() {
+        assert!(matches!(ImportSource::parse("Paste"), Err(Error::InvalidInput(m)) if m.contains("cannot be imported")));
+        assert_eq!(ImportSource::parse("CopyQ").unwrap(), ImportSource::CopyQ);
+        assert!(ImportSource::parse("Ditto").is_err());
+    }
+}
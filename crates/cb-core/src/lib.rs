@@ -1,285 +1,662 @@
+pub mod blobs;
+pub mod cipher;
+pub mod error;
+pub mod export;
+pub mod fuzzy;
+pub mod ignore;
+pub mod import;
+pub mod migrations;
 pub mod models;
+pub mod query;
+pub mod retention;
+pub mod sensitive;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod storage;
+pub mod thumbnail;
 
+pub use error::{Error, Result};
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
 use std::sync::Mutex;
+use cipher::{CipherConfig, KeySpec};
+use export::ImageExport;
+use fuzzy::FuzzyOptions;
+use ignore::IgnoreKind;
+use import::ImportSource;
+use retention::RetentionPolicy;
+use sensitive::{Rule, RuleSet};
 use storage::Storage;
-use models::ContentType;
+use models::{BackupProgress, ContentType, Cursor, EntryFilter, PageDirection, SaveOutcome, SortOrder};
 
 static STORAGE: Mutex<Option<Storage>> = Mutex::new(None);
+/// Progress of the running backup or restore. Kept outside `STORAGE`,
+/// whose lock the operation holds.
+static BACKUP_PROGRESS: Mutex<Option<BackupProgress>> = Mutex::new(None);
+#[cfg(feature = "server")]
+static SERVER: Mutex<Option<server::Server>> = Mutex::new(None);
 
 fn json_ok<T: serde::Serialize>(data: &T) -> String {
     match serde_json::to_string(data) {
         Ok(json) => format!("{{\"ok\":{}}}", json),
         Err(e) => {
             eprintln!("Failed to serialize: {e}");
-            json_error(&format!("Serialization failed: {e}"))
+            json_error(&Error::from(e))
         }
     }
 }
 
-fn json_error(msg: &str) -> String {
-    format!("{{\"error\":{}}}", serde_json::to_string(msg).unwrap_or_else(|_| "\"unknown error\"".to_string()))
+fn json_error(err: &Error) -> String {
+    serde_json::json!({
+        "error": {
+            "code": err.code(),
+            "name": err.name(),
+            "message": err.to_string(),
+        }
+    }).to_string()
+}
+
+fn json_result<T: serde::Serialize>(result: Result<T>) -> String {
+    match result {
+        Ok(data) => json_ok(&data),
+        Err(e) => json_error(&e),
+    }
+}
+
+/// Logs a failed FFI call and flattens the result for the legacy
+/// bool / Option / sentinel-returning functions.
+fn report<T>(context: &str, result: Result<T>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("{context}: {e}");
+            None
+        }
+    }
+}
+
+fn with_storage<T>(f: impl FnOnce(&Storage) -> Result<T>) -> Result<T> {
+    let guard = STORAGE.lock().map_err(|_| Error::LockPoisoned)?;
+    match guard.as_ref() {
+        Some(storage) => f(storage),
+        None => Err(Error::NotInitialized),
+    }
+}
+
+fn with_storage_mut<T>(f: impl FnOnce(&mut Storage) -> Result<T>) -> Result<T> {
+    let mut guard = STORAGE.lock().map_err(|_| Error::LockPoisoned)?;
+    match guard.as_mut() {
+        Some(storage) => f(storage),
+        None => Err(Error::NotInitialized),
+    }
 }
 
 #[swift_bridge::bridge]
 mod ffi {
     extern "Rust" {
         fn init_storage(db_path: String, encryption_key: String) -> bool;
+        fn init_storage_result(db_path: String, encryption_key: String) -> String;
+        fn init_storage_with_config(db_path: String, config_json: String) -> String;
         fn migrate_database(plain_path: String, encrypted_path: String, encryption_key: String) -> bool;
-        fn save_clipboard_entry(content_type: String, text: String, source_app: String) -> bool;
-        fn save_clipboard_image(image_data: &[u8], source_app: String) -> bool;
+        fn migrate_database_result(plain_path: String, encrypted_path: String, encryption_key: String) -> String;
+        fn rekey_database(old_key: String, new_key: String) -> bool;
+        fn rekey_database_result(old_key: String, new_key: String) -> String;
+        fn rekey_database_with_keys(old_key_json: String, new_key_json: String) -> String;
+        fn backup_database(path: String, backup_key_json: String) -> String;
+        fn restore_database(path: String, backup_key_json: String) -> String;
+        fn get_backup_progress() -> String;
+        fn export_history(path: String, image_dir: String) -> String;
+        fn import_history(path: String, image_dir: String) -> String;
+        fn import_from_app(source: String, path: String) -> String;
+        fn start_server(socket_path: String, token_path: String) -> String;
+        fn stop_server() -> String;
+        fn save_clipboard_entry(content_type: String, text: String, source_app: String, bundle_id: String) -> bool;
+        fn save_clipboard_entry_result(content_type: String, text: String, source_app: String, bundle_id: String) -> String;
+        fn save_clipboard_entry_with_ttl(
+            content_type: String,
+            text: String,
+            source_app: String,
+            bundle_id: String,
+            ttl_seconds: u32,
+        ) -> String;
+        fn save_clipboard_image(image_data: &[u8], source_app: String, bundle_id: String) -> bool;
+        fn save_clipboard_image_result(image_data: &[u8], source_app: String, bundle_id: String) -> String;
         fn get_recent_entries(limit: i32) -> String;
         fn delete_entry(id: i64) -> bool;
+        fn delete_entry_result(id: i64) -> String;
         fn get_entry_text(id: i64) -> Option<String>;
         fn get_entry_image(id: i64) -> Option<Vec<u8>>;
+        fn get_entry_thumbnail(id: i64, max_px: u32) -> Option<Vec<u8>>;
         fn search_entries(query: String, limit: i32) -> String;
+        fn search_entries_with_snippets(query: String, limit: i32) -> String;
+        fn search_entries_ranked(query: String, limit: i32, sort_order: String) -> String;
+        fn search_entries_fuzzy(query: String, limit: i32, min_hits: i32, scan_budget: i32) -> String;
         fn get_entries_before(before_timestamp: i64, limit: i32) -> String;
         fn touch_entry(id: i64) -> bool;
+        fn touch_entry_result(id: i64) -> String;
         fn cleanup_old_entries(max_age_days: i32) -> i64;
+        fn cleanup_old_entries_result(max_age_days: i32) -> String;
+        fn set_entry_expiry(id: i64, expires_at: i64) -> String;
+        fn purge_expired() -> i64;
+        fn purge_expired_result() -> String;
+        fn apply_retention(policy_json: String) -> String;
+        fn pin_entry(id: i64) -> String;
+        fn unpin_entry(id: i64) -> String;
+        fn get_pinned_entries() -> String;
+        fn reorder_pins(ids_json: String) -> String;
+        fn create_tag(name: String) -> String;
+        fn rename_tag(id: i64, new_name: String) -> String;
+        fn delete_tag(id: i64) -> String;
+        fn list_tags() -> String;
+        fn tag_entry(entry_id: i64, tag_id: i64) -> String;
+        fn untag_entry(entry_id: i64, tag_id: i64) -> String;
+        fn get_entries_by_tag(tag_id: i64, cursor: String, direction: String, limit: i32) -> String;
+        fn list_entries(filter_json: String, cursor: String, limit: i32) -> String;
+        fn get_entries_page(filter_json: String, cursor: String, direction: String, limit: i32) -> String;
+        fn search_entries_page(query: String, cursor: String, direction: String, limit: i32) -> String;
+        fn get_sensitive_rules() -> String;
+        fn set_sensitive_rules(rules_json: String) -> String;
+        fn add_ignored_app(kind: String, pattern: String) -> String;
+        fn remove_ignored_app(id: i64) -> String;
+        fn list_ignored_apps() -> String;
+        fn purge_ignored_app_entries(id: i64) -> String;
     }
 }
 
+fn open_storage(db_path: &str, encryption_key: &str) -> Result<()> {
+    let key = if encryption_key.is_empty() { None } else { Some(encryption_key) };
+    install_storage(Storage::new(db_path, key)?)
+}
+
+fn install_storage(storage: Storage) -> Result<()> {
+    let mut guard = STORAGE.lock().map_err(|_| Error::LockPoisoned)?;
+    *guard = Some(storage);
+    Ok(())
+}
+
 fn init_storage(db_path: String, encryption_key: String) -> bool {
-    let key = if encryption_key.is_empty() { None } else { Some(encryption_key.as_str()) };
-    match Storage::new(&db_path, key) {
-        Ok(s) => {
-            let mut guard = match STORAGE.lock() {
-                Ok(g) => g,
-                Err(e) => {
-                    eprintln!("Storage lock poisoned: {e}");
-                    return false;
-                }
-            };
-            *guard = Some(s);
-            true
-        }
-        Err(e) => {
-            eprintln!("Failed to init storage: {e}");
-            false
-        }
-    }
+    report("Failed to init storage", open_storage(&db_path, &encryption_key)).is_some()
+}
+
+fn init_storage_result(db_path: String, encryption_key: String) -> String {
+    json_result(open_storage(&db_path, &encryption_key).map(|_| true))
+}
+
+/// Opens the database with a JSON `CipherConfig`, e.g.
+/// `{"key": {"RawHex": "…"}, "cipher_compatibility": 4}`.
+fn init_storage_with_config(db_path: String, config_json: String) -> String {
+    let config = serde_json::from_str::<CipherConfig>(&config_json)
+        .map_err(|e| Error::InvalidInput(e.to_string()));
+    let opened = config.and_then(|config| install_storage(Storage::open(&db_path, &config)?));
+    json_result(opened.map(|_| true))
 }
 
 fn migrate_database(plain_path: String, encrypted_path: String, encryption_key: String) -> bool {
-    match Storage::migrate_to_encrypted(&plain_path, &encrypted_path, &encryption_key) {
-        Ok(_) => true,
-        Err(e) => {
-            eprintln!("Migration failed: {e}");
-            false
-        }
-    }
+    report(
+        "Migration failed",
+        Storage::migrate_to_encrypted(&plain_path, &encrypted_path, &encryption_key),
+    ).is_some()
 }
 
-fn save_clipboard_entry(content_type: String, text: String, source_app: String) -> bool {
-    let guard = match STORAGE.lock() {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Storage lock poisoned: {e}");
-            return false;
-        }
+fn migrate_database_result(plain_path: String, encrypted_path: String, encryption_key: String) -> String {
+    json_result(
+        Storage::migrate_to_encrypted(&plain_path, &encrypted_path, &encryption_key).map(|_| true),
+    )
+}
+
+/// Rotates the passphrase of the open database; see `Storage::rotate_key`.
+/// The caller stores `new_key` (e.g. in the Keychain) only after this
+/// succeeds.
+fn rekey_database(old_key: String, new_key: String) -> bool {
+    report("Rekey failed", rotate_passphrase(old_key, new_key)).is_some()
+}
+
+fn rekey_database_result(old_key: String, new_key: String) -> String {
+    json_result(rotate_passphrase(old_key, new_key).map(|_| true))
+}
+
+fn rotate_passphrase(old_key: String, new_key: String) -> Result<()> {
+    let (old_key, new_key) = (KeySpec::Passphrase(old_key), KeySpec::Passphrase(new_key));
+    with_storage_mut(|storage| storage.rotate_key(&old_key, &new_key))
+}
+
+/// `rekey_database_result` for any key type, with both keys as JSON
+/// `KeySpec`s, e.g. `{"Argon2id": {"passphrase": "…"}}`. Changing the key
+/// type is allowed.
+fn rekey_database_with_keys(old_key_json: String, new_key_json: String) -> String {
+    json_result(rotate_keys(&old_key_json, &new_key_json).map(|_| true))
+}
+
+fn rotate_keys(old_key_json: &str, new_key_json: &str) -> Result<()> {
+    let (Some(old_key), Some(new_key)) = (parse_key_spec(old_key_json)?, parse_key_spec(new_key_json)?) else {
+        return Err(Error::InvalidInput("both keys are required".to_string()));
     };
-    if let Some(ref storage) = *guard {
-        let ct = ContentType::from_str(&content_type);
-        match storage.insert_text_entry(&ct, &text, &source_app) {
-            Ok(_) => true,
-            Err(e) => {
-                eprintln!("Failed to save entry: {e}");
-                false
-            }
-        }
+    with_storage_mut(|storage| storage.rotate_key(&old_key, &new_key))
+}
+
+/// A JSON `KeySpec`; an empty string means no key.
+fn parse_key_spec(key_json: &str) -> Result<Option<KeySpec>> {
+    if key_json.trim().is_empty() {
+        Ok(None)
     } else {
-        eprintln!("Storage not initialized");
-        false
+        serde_json::from_str(key_json).map(Some).map_err(|e| Error::InvalidInput(e.to_string()))
     }
 }
 
-fn save_clipboard_image(image_data: &[u8], source_app: String) -> bool {
-    let guard = match STORAGE.lock() {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Storage lock poisoned: {e}");
-            return false;
-        }
-    };
-    if let Some(ref storage) = *guard {
-        match storage.insert_image_entry(image_data, &source_app) {
-            Ok(_) => true,
-            Err(e) => {
-                eprintln!("Failed to save image: {e}");
-                false
-            }
-        }
-    } else {
-        eprintln!("Storage not initialized");
-        false
+fn publish_backup_progress(progress: Option<BackupProgress>) {
+    if let Ok(mut current) = BACKUP_PROGRESS.lock() {
+        *current = progress;
     }
 }
 
-fn get_recent_entries(limit: i32) -> String {
-    let guard = match STORAGE.lock() {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Storage lock poisoned: {e}");
-            return json_error(&format!("Storage lock poisoned: {e}"));
-        }
-    };
-    if let Some(ref storage) = *guard {
-        match storage.get_recent_entries(limit) {
-            Ok(entries) => json_ok(&entries),
-            Err(e) => {
-                eprintln!("Failed to get entries: {e}");
-                json_error(&format!("Failed to get entries: {e}"))
-            }
-        }
-    } else {
-        json_error("Storage not initialized")
+/// Writes an encrypted copy of the open database to `path`; see
+/// `Storage::backup_to`. `backup_key_json` is a JSON `KeySpec`, e.g.
+/// `{"Passphrase": "…"}`, or empty for a plaintext database.
+fn backup_database(path: String, backup_key_json: String) -> String {
+    let result = parse_key_spec(&backup_key_json).and_then(|key| with_storage(|storage| {
+        storage.backup_to(&path, key.as_ref(), |progress| publish_backup_progress(Some(progress)))
+    }));
+    publish_backup_progress(None);
+    json_result(result.map(|_| true))
+}
+
+/// Replaces the open database with the backup at `path`; see
+/// `Storage::restore_from`. `backup_key_json` is as for `backup_database`.
+/// Swift reloads its lists afterwards.
+fn restore_database(path: String, backup_key_json: String) -> String {
+    let result = parse_key_spec(&backup_key_json).and_then(|key| with_storage_mut(|storage| {
+        storage.restore_from(&path, key.as_ref(), |progress| publish_backup_progress(Some(progress)))
+    }));
+    publish_backup_progress(None);
+    json_result(result.map(|_| true))
+}
+
+/// Polled from another thread while `backup_database` or
+/// `restore_database` runs; `{"ok": null}` when neither is.
+fn get_backup_progress() -> String {
+    json_result(BACKUP_PROGRESS.lock().map(|progress| *progress).map_err(|_| Error::LockPoisoned))
+}
+
+/// Writes the history to `path` as NDJSON. Images are inline unless
+/// `image_dir` names a directory for side files.
+fn export_history(path: String, image_dir: String) -> String {
+    let images = if image_dir.is_empty() { ImageExport::Inline } else { ImageExport::Files(Path::new(&image_dir)) };
+    let result = with_storage(|storage| {
+        let mut out = BufWriter::new(File::create(&path)?);
+        storage.export_history(&mut out, images)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&path);
     }
+    json_result(result)
 }
 
-fn delete_entry(id: i64) -> bool {
-    let guard = match STORAGE.lock() {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Storage lock poisoned: {e}");
-            return false;
-        }
-    };
-    if let Some(ref storage) = *guard {
-        match storage.delete_entry(id) {
-            Ok(deleted) => deleted,
-            Err(e) => {
-                eprintln!("Failed to delete entry: {e}");
-                false
-            }
+/// Merges an export made by `export_history`; `image_dir` is where its
+/// image side files are, or empty for inline images.
+fn import_history(path: String, image_dir: String) -> String {
+    let image_dir = (!image_dir.is_empty()).then(|| Path::new(&image_dir));
+    json_result(with_storage(|storage| {
+        storage.import_history(BufReader::new(File::open(&path)?), image_dir)
+    }))
+}
+
+/// Imports another clipboard manager's history; `source` is an
+/// `ImportSource` name such as `"Maccy"`.
+fn import_from_app(source: String, path: String) -> String {
+    json_result(ImportSource::parse(&source).and_then(|source| {
+        with_storage(|storage| storage.import_from(source, Path::new(&path)))
+    }))
+}
+
+/// Serves the storage over a Unix socket; see `server`. Fails if this
+/// process already runs a server or cb-core was built without `server`.
+#[cfg(feature = "server")]
+fn start_server(socket_path: String, token_path: String) -> String {
+    json_result(SERVER.lock().map_err(|_| Error::LockPoisoned).and_then(|mut running| {
+        if running.is_some() {
+            return Err(Error::InvalidInput("the server is already running".to_string()));
         }
-    } else {
-        false
-    }
+        *running = Some(server::Server::start(&STORAGE, server::ServerConfig::new(socket_path, token_path))?);
+        Ok(true)
+    }))
+}
+
+/// Returns whether a server was running.
+#[cfg(feature = "server")]
+fn stop_server() -> String {
+    json_result(SERVER.lock().map_err(|_| Error::LockPoisoned).map(|mut running| running.take().is_some()))
+}
+
+#[cfg(not(feature = "server"))]
+fn start_server(_socket_path: String, _token_path: String) -> String {
+    json_error(&Error::InvalidInput("cb-core was built without the server feature".to_string()))
+}
+
+#[cfg(not(feature = "server"))]
+fn stop_server() -> String {
+    json_ok(&false)
+}
+
+/// Runs `insert` unless the source app is on the ignore list. `bundle_id`
+/// may be empty when Swift cannot tell.
+fn save_unless_ignored(
+    source_app: &str,
+    bundle_id: &str,
+    insert: impl FnOnce(&Storage) -> Result<SaveOutcome>,
+) -> Result<SaveOutcome> {
+    with_storage(|storage| match storage.find_ignored_app(source_app, bundle_id)? {
+        Some(app) => Ok(SaveOutcome::Ignored { pattern: app.pattern }),
+        None => insert(storage),
+    })
+}
+
+fn save_text(content_type: &str, text: &str, source_app: &str, bundle_id: &str) -> Result<SaveOutcome> {
+    let ct = ContentType::from_str(content_type);
+    save_unless_ignored(source_app, bundle_id, |storage| {
+        storage.insert_text_entry_from(&ct, text, source_app, bundle_id, None)
+    })
+}
+
+/// Returns true when the copy was handled, including when it was skipped or
+/// ignored; the `_result` variant tells these apart.
+fn save_clipboard_entry(content_type: String, text: String, source_app: String, bundle_id: String) -> bool {
+    report("Failed to save entry", save_text(&content_type, &text, &source_app, &bundle_id)).is_some()
+}
+
+fn save_clipboard_entry_result(content_type: String, text: String, source_app: String, bundle_id: String) -> String {
+    json_result(save_text(&content_type, &text, &source_app, &bundle_id))
+}
+
+/// Like `save_clipboard_entry_result`, for copies that should vanish after
+/// `ttl_seconds`.
+fn save_clipboard_entry_with_ttl(
+    content_type: String,
+    text: String,
+    source_app: String,
+    bundle_id: String,
+    ttl_seconds: u32,
+) -> String {
+    let ct = ContentType::from_str(&content_type);
+    json_result(save_unless_ignored(&source_app, &bundle_id, |storage| {
+        storage.insert_text_entry_from(&ct, &text, &source_app, &bundle_id, Some(ttl_seconds))
+    }))
+}
+
+fn save_image(image_data: &[u8], source_app: &str, bundle_id: &str) -> Result<SaveOutcome> {
+    // Decode outside the STORAGE lock so a large image does not block other calls
+    let thumb = thumbnail::generate(image_data, thumbnail::STORED_MAX_PX).ok();
+    save_unless_ignored(source_app, bundle_id, |storage| {
+        storage.insert_image_entry_with_thumbnail(image_data, source_app, bundle_id, thumb)
+    })
+}
+
+fn save_clipboard_image(image_data: &[u8], source_app: String, bundle_id: String) -> bool {
+    report("Failed to save image", save_image(image_data, &source_app, &bundle_id)).is_some()
+}
+
+fn save_clipboard_image_result(image_data: &[u8], source_app: String, bundle_id: String) -> String {
+    json_result(save_image(image_data, &source_app, &bundle_id))
+}
+
+fn get_recent_entries(limit: i32) -> String {
+    json_result(with_storage(|storage| storage.get_recent_entries(limit)))
+}
+
+fn delete_entry(id: i64) -> bool {
+    report("Failed to delete entry", with_storage(|storage| storage.delete_entry(id)))
+        .unwrap_or(false)
+}
+
+fn delete_entry_result(id: i64) -> String {
+    json_result(with_storage(|storage| storage.delete_entry(id)))
 }
 
 fn get_entry_text(id: i64) -> Option<String> {
-    let guard = match STORAGE.lock() {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Storage lock poisoned: {e}");
-            return None;
-        }
-    };
-    if let Some(ref storage) = *guard {
-        match storage.get_entry_text(id) {
-            Ok(text) => text,
-            Err(e) => {
-                eprintln!("Failed to get entry text: {e}");
-                None
-            }
-        }
-    } else {
-        None
-    }
+    report("Failed to get entry text", with_storage(|storage| storage.get_entry_text(id)))
+        .flatten()
 }
 
 fn get_entry_image(id: i64) -> Option<Vec<u8>> {
-    let guard = match STORAGE.lock() {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Storage lock poisoned: {e}");
-            return None;
-        }
-    };
-    if let Some(ref storage) = *guard {
-        match storage.get_entry_image(id) {
-            Ok(data) => data,
-            Err(e) => {
-                eprintln!("Failed to get entry image: {e}");
-                None
-            }
-        }
-    } else {
-        None
-    }
+    report("Failed to get entry image", with_storage(|storage| storage.get_entry_image(id)))
+        .flatten()
+}
+
+fn get_entry_thumbnail(id: i64, max_px: u32) -> Option<Vec<u8>> {
+    report("Failed to get entry thumbnail", with_storage(|storage| storage.get_entry_thumbnail(id, max_px)))
+        .flatten()
 }
 
 fn search_entries(query: String, limit: i32) -> String {
-    let guard = match STORAGE.lock() {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Storage lock poisoned: {e}");
-            return json_error(&format!("Storage lock poisoned: {e}"));
-        }
+    json_result(with_storage(|storage| storage.search_entries(&query, limit)))
+}
+
+fn search_entries_with_snippets(query: String, limit: i32) -> String {
+    json_result(with_storage(|storage| storage.search_entries_with_snippets(&query, limit)))
+}
+
+/// `sort_order` is `"Recent"` or `"Relevance"`; anything else means `"Recent"`.
+fn search_entries_ranked(query: String, limit: i32, sort_order: String) -> String {
+    let sort_order = SortOrder::from_str(&sort_order);
+    json_result(with_storage(|storage| storage.search_entries_ranked(&query, limit, sort_order)))
+}
+
+/// `min_hits` / `scan_budget` of zero or less use `FuzzyOptions::default()`.
+fn search_entries_fuzzy(query: String, limit: i32, min_hits: i32, scan_budget: i32) -> String {
+    let defaults = FuzzyOptions::default();
+    let options = FuzzyOptions {
+        min_hits: if min_hits > 0 { min_hits as usize } else { defaults.min_hits },
+        scan_budget: if scan_budget > 0 { scan_budget as usize } else { defaults.scan_budget },
     };
-    if let Some(ref storage) = *guard {
-        match storage.search_entries(&query, limit) {
-            Ok(entries) => json_ok(&entries),
-            Err(e) => {
-                eprintln!("Failed to search entries: {e}");
-                json_error(&format!("Failed to search entries: {e}"))
-            }
-        }
-    } else {
-        json_error("Storage not initialized")
-    }
+    json_result(with_storage(|storage| storage.search_entries_fuzzy(&query, limit, &options)))
 }
 
 fn get_entries_before(before_timestamp: i64, limit: i32) -> String {
-    let guard = match STORAGE.lock() {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Storage lock poisoned: {e}");
-            return json_error(&format!("Storage lock poisoned: {e}"));
-        }
-    };
-    if let Some(ref storage) = *guard {
-        match storage.get_entries_before(before_timestamp, limit) {
-            Ok(entries) => json_ok(&entries),
-            Err(e) => {
-                eprintln!("Failed to get entries before: {e}");
-                json_error(&format!("Failed to get entries before: {e}"))
-            }
-        }
-    } else {
-        json_error("Storage not initialized")
-    }
+    json_result(with_storage(|storage| storage.get_entries_before(before_timestamp, limit)))
 }
 
 fn touch_entry(id: i64) -> bool {
-    let guard = match STORAGE.lock() {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Storage lock poisoned: {e}");
-            return false;
-        }
-    };
-    if let Some(ref storage) = *guard {
-        match storage.touch_entry(id) {
-            Ok(touched) => touched,
-            Err(e) => {
-                eprintln!("Failed to touch entry: {e}");
-                false
-            }
-        }
-    } else {
-        eprintln!("Storage not initialized");
-        false
-    }
+    report("Failed to touch entry", with_storage(|storage| storage.touch_entry(id)))
+        .unwrap_or(false)
+}
+
+fn touch_entry_result(id: i64) -> String {
+    json_result(with_storage(|storage| storage.touch_entry(id)))
 }
 
 fn cleanup_old_entries(max_age_days: i32) -> i64 {
-    let guard = match STORAGE.lock() {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Storage lock poisoned: {e}");
-            return -1;
-        }
-    };
-    if let Some(ref storage) = *guard {
-        match storage.cleanup_old_entries(max_age_days) {
-            Ok(count) => count as i64,
-            Err(e) => {
-                eprintln!("Failed to cleanup old entries: {e}");
-                -1
-            }
-        }
+    report(
+        "Failed to cleanup old entries",
+        with_storage(|storage| storage.cleanup_old_entries(max_age_days)),
+    ).map_or(-1, |count| count as i64)
+}
+
+fn cleanup_old_entries_result(max_age_days: i32) -> String {
+    json_result(with_storage(|storage| storage.cleanup_old_entries(max_age_days)))
+}
+
+/// `expires_at` is in milliseconds; zero or less clears the expiry.
+/// `{"ok": false}` when the entry does not exist or is pinned.
+fn set_entry_expiry(id: i64, expires_at: i64) -> String {
+    let expires_at = if expires_at > 0 { Some(expires_at) } else { None };
+    json_result(with_storage(|storage| storage.set_entry_expiry(id, expires_at)))
+}
+
+fn purge_expired() -> i64 {
+    report("Failed to purge expired entries", with_storage(|storage| storage.purge_expired()))
+        .map_or(-1, |count| count as i64)
+}
+
+fn purge_expired_result() -> String {
+    json_result(with_storage(|storage| storage.purge_expired()))
+}
+
+/// `policy_json` is a `RetentionPolicy` object; see `retention` for the format.
+fn apply_retention(policy_json: String) -> String {
+    json_result(
+        serde_json::from_str::<RetentionPolicy>(&policy_json)
+            .map_err(|e| Error::InvalidInput(e.to_string()))
+            .and_then(|policy| with_storage(|storage| storage.apply_retention(&policy))),
+    )
+}
+
+/// `{"ok": false}` when there is no such entry.
+fn pin_entry(id: i64) -> String {
+    json_result(with_storage(|storage| storage.pin_entry(id)))
+}
+
+/// `{"ok": false}` when there is no such pinned entry.
+fn unpin_entry(id: i64) -> String {
+    json_result(with_storage(|storage| storage.unpin_entry(id)))
+}
+
+fn get_pinned_entries() -> String {
+    json_result(with_storage(|storage| storage.get_pinned_entries()))
+}
+
+/// `ids_json` is a JSON array of entry ids, e.g. `[3, 1, 2]`.
+fn reorder_pins(ids_json: String) -> String {
+    json_result(
+        serde_json::from_str::<Vec<i64>>(&ids_json)
+            .map_err(|e| Error::InvalidInput(e.to_string()))
+            .and_then(|ids| with_storage(|storage| storage.reorder_pins(&ids)))
+            .map(|_| true),
+    )
+}
+
+fn create_tag(name: String) -> String {
+    json_result(with_storage(|storage| storage.create_tag(&name)))
+}
+
+fn rename_tag(id: i64, new_name: String) -> String {
+    json_result(with_storage(|storage| storage.rename_tag(id, &new_name)))
+}
+
+/// `{"ok": false}` when there is no such tag.
+fn delete_tag(id: i64) -> String {
+    json_result(with_storage(|storage| storage.delete_tag(id)))
+}
+
+fn list_tags() -> String {
+    json_result(with_storage(|storage| storage.list_tags()))
+}
+
+/// `{"ok": false}` when the entry or tag does not exist, or the link
+/// already does.
+fn tag_entry(entry_id: i64, tag_id: i64) -> String {
+    json_result(with_storage(|storage| storage.tag_entry(entry_id, tag_id)))
+}
+
+/// `{"ok": false}` when the entry does not carry the tag.
+fn untag_entry(entry_id: i64, tag_id: i64) -> String {
+    json_result(with_storage(|storage| storage.untag_entry(entry_id, tag_id)))
+}
+
+/// Paged like `get_entries_page`.
+fn get_entries_by_tag(tag_id: i64, cursor: String, direction: String, limit: i32) -> String {
+    let direction = PageDirection::from_str(&direction);
+    json_result(parse_cursor(&cursor).and_then(|cursor| {
+        with_storage(|storage| storage.get_entries_by_tag(tag_id, cursor.as_ref(), direction, limit))
+    }))
+}
+
+/// An empty string means no filter.
+fn parse_filter(filter_json: &str) -> Result<EntryFilter> {
+    if filter_json.trim().is_empty() {
+        Ok(EntryFilter::default())
     } else {
-        -1
+        serde_json::from_str(filter_json).map_err(|e| Error::InvalidInput(e.to_string()))
+    }
+}
+
+/// An empty string means the first page.
+fn parse_cursor(cursor: &str) -> Result<Option<Cursor>> {
+    if cursor.is_empty() { Ok(None) } else { Cursor::decode(cursor).map(Some) }
+}
+
+/// `filter_json` is an `EntryFilter` object, e.g. `{"content_types":["Image"]}`;
+/// an empty string means no filter. `cursor` is a previous page's
+/// `next_cursor`, or empty for the newest page.
+fn list_entries(filter_json: String, cursor: String, limit: i32) -> String {
+    json_result(parse_filter(&filter_json).and_then(|filter| {
+        let cursor = parse_cursor(&cursor)?;
+        with_storage(|storage| storage.list_entries(&filter, cursor.as_ref(), limit))
+    }))
+}
+
+/// `cursor` is a `next_cursor` / `prev_cursor` from a previous page, or empty
+/// for the first page; `direction` is `"Older"` (default) or `"Newer"`.
+fn get_entries_page(filter_json: String, cursor: String, direction: String, limit: i32) -> String {
+    let direction = PageDirection::from_str(&direction);
+    json_result(parse_filter(&filter_json).and_then(|filter| {
+        let cursor = parse_cursor(&cursor)?;
+        with_storage(|storage| storage.get_entries_page(&filter, cursor.as_ref(), direction, limit))
+    }))
+}
+
+fn search_entries_page(query: String, cursor: String, direction: String, limit: i32) -> String {
+    let direction = PageDirection::from_str(&direction);
+    json_result(parse_cursor(&cursor).and_then(|cursor| {
+        with_storage(|storage| storage.search_entries_page(&query, cursor.as_ref(), direction, limit))
+    }))
+}
+
+fn get_sensitive_rules() -> String {
+    json_result(with_storage(|storage| Ok(storage.sensitive_rules().rules().to_vec())))
+}
+
+/// Replaces the sensitive rules and saves them in the database. An empty
+/// array turns detection off; pass the output of `get_sensitive_rules` to
+/// extend the built-in set.
+fn set_sensitive_rules(rules_json: String) -> String {
+    json_result(
+        serde_json::from_str::<Vec<Rule>>(&rules_json)
+            .map_err(|e| Error::InvalidInput(e.to_string()))
+            .and_then(RuleSet::new)
+            .and_then(|rules| with_storage_mut(|storage| {
+                storage.set_sensitive_rules(rules)?;
+                Ok(true)
+            })),
+    )
+}
+
+/// `kind` is `"Name"`, `"BundleId"` or `"Glob"`; anything else is `invalid_input`.
+fn add_ignored_app(kind: String, pattern: String) -> String {
+    json_result(
+        IgnoreKind::parse(&kind)
+            .and_then(|kind| with_storage(|storage| storage.add_ignored_app(kind, &pattern))),
+    )
+}
+
+/// `{"ok": false}` when no rule has this id.
+fn remove_ignored_app(id: i64) -> String {
+    json_result(with_storage(|storage| storage.remove_ignored_app(id)))
+}
+
+fn list_ignored_apps() -> String {
+    json_result(with_storage(|storage| storage.list_ignored_apps()))
+}
+
+fn purge_ignored_app_entries(id: i64) -> String {
+    json_result(with_storage(|storage| storage.purge_ignored_app_entries(id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_error_envelope() {
+        let json: serde_json::Value = serde_json::from_str(&json_error(&Error::WrongKey)).unwrap();
+        assert_eq!(json["error"]["code"], 3);
+        assert_eq!(json["error"]["name"], "wrong_key");
+        assert!(json["error"]["message"].is_string());
+    }
+
+    #[test]
+    fn test_json_result_ok_envelope() {
+        let json: serde_json::Value = serde_json::from_str(&json_result(Ok(42))).unwrap();
+        assert_eq!(json["ok"], 42);
     }
 }
@@ -0,0 +1,663 @@
+//! Versioned schema migrations keyed on `PRAGMA user_version`.
+//!
+//! Each migration runs exactly once, inside its own transaction, and bumps
+//! `user_version` on commit. Databases created before versioning existed
+//! report version 0 and are brought forward by the same list; the early
+//! migrations therefore tolerate schema objects that already exist.
+
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+use crate::error::{Error, Result};
+use crate::blobs;
+use crate::storage::{content_hash, now_millis};
+use crate::thumbnail;
+
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    up: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "create clipboard_entries", up: create_entries_table },
+    Migration { version: 2, description: "add copy_count", up: add_copy_count },
+    Migration { version: 3, description: "add first_copied_at", up: add_first_copied_at },
+    Migration { version: 4, description: "convert timestamps to milliseconds", up: timestamps_to_millis },
+    Migration { version: 5, description: "create clipboard_fts", up: create_fts },
+    Migration { version: 6, description: "add content_hash", up: add_content_hash },
+    Migration { version: 7, description: "add pinned / pin_order", up: add_pinned },
+    Migration { version: 8, description: "create tags / entry_tags", up: create_tags },
+    Migration { version: 9, description: "create clipboard_fts_trigram", up: create_trigram_fts },
+    Migration { version: 10, description: "add content_type / source_app indexes", up: add_filter_indexes },
+    Migration { version: 11, description: "create thumbnails", up: create_thumbnails },
+    Migration { version: 12, description: "move image_data into blobs", up: move_images_to_blobs },
+    Migration { version: 13, description: "add sensitive / expires_at", up: add_sensitive },
+    Migration { version: 14, description: "create ignored_apps", up: create_ignored_apps },
+    Migration { version: 15, description: "create sensitive_rules", up: create_sensitive_rules },
+    Migration { version: 16, description: "add source_bundle_id", up: add_source_bundle_id },
+];
+
+/// Highest schema version this build of cb-core understands.
+pub const LATEST_VERSION: i64 = MIGRATIONS.len() as i64;
+
+pub fn schema_version(conn: &Connection) -> Result<i64> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+/// Applies every migration newer than the database's `user_version`.
+/// Refuses to touch a database written by a newer cb-core.
+pub fn run(conn: &Connection) -> Result<()> {
+    let current = schema_version(conn)?;
+    if current > LATEST_VERSION {
+        return Err(Error::UnsupportedSchema { found: current, supported: LATEST_VERSION });
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.unchecked_transaction()?;
+        (migration.up)(&tx)?;
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn has_column(tx: &Transaction, table: &str, column: &str) -> rusqlite::Result<bool> {
+    tx.query_row(
+        "SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |_| Ok(()),
+    ).optional().map(|found| found.is_some())
+}
+
+fn create_entries_table(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS clipboard_entries (
+            id            INTEGER PRIMARY KEY AUTOINCREMENT,
+            content_type  TEXT NOT NULL,
+            text_content  TEXT,
+            image_data    BLOB,
+            source_app    TEXT,
+            created_at    INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_created_at
+        ON clipboard_entries(created_at DESC);"
+    )
+}
+
+fn add_copy_count(tx: &Transaction) -> rusqlite::Result<()> {
+    if !has_column(tx, "clipboard_entries", "copy_count")? {
+        tx.execute_batch(
+            "ALTER TABLE clipboard_entries ADD COLUMN copy_count INTEGER NOT NULL DEFAULT 1;"
+        )?;
+    }
+    Ok(())
+}
+
+fn add_first_copied_at(tx: &Transaction) -> rusqlite::Result<()> {
+    if !has_column(tx, "clipboard_entries", "first_copied_at")? {
+        tx.execute_batch(
+            "ALTER TABLE clipboard_entries ADD COLUMN first_copied_at INTEGER NOT NULL DEFAULT 0;"
+        )?;
+    }
+    tx.execute_batch(
+        "UPDATE clipboard_entries SET first_copied_at = created_at WHERE first_copied_at = 0;"
+    )
+}
+
+fn timestamps_to_millis(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "UPDATE clipboard_entries SET created_at = created_at * 1000 WHERE created_at > 0 AND created_at < 10000000000;
+         UPDATE clipboard_entries SET first_copied_at = first_copied_at * 1000 WHERE first_copied_at > 0 AND first_copied_at < 10000000000;"
+    )
+}
+
+fn create_fts(tx: &Transaction) -> rusqlite::Result<()> {
+    // Legacy databases may already have the table and triggers but an index
+    // that is out of sync with the content table, so always rebuild once.
+    tx.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS clipboard_fts
+        USING fts5(text_content, content='clipboard_entries', content_rowid='id');
+
+        CREATE TRIGGER IF NOT EXISTS clipboard_entries_ai
+        AFTER INSERT ON clipboard_entries
+        BEGIN
+            INSERT INTO clipboard_fts(rowid, text_content)
+            VALUES (new.id, new.text_content);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS clipboard_entries_ad
+        AFTER DELETE ON clipboard_entries
+        BEGIN
+            INSERT INTO clipboard_fts(clipboard_fts, rowid, text_content)
+            VALUES ('delete', old.id, old.text_content);
+        END;
+
+        INSERT INTO clipboard_fts(clipboard_fts) VALUES ('rebuild');"
+    )
+}
+
+/// Adds `content_hash` and backfills it. Rows whose digest is already taken
+/// are merged into the existing row (copy counts summed, earliest
+/// `first_copied_at` and latest `created_at` kept) and deleted, so the unique
+/// index can be created afterwards.
+fn add_content_hash(tx: &Transaction) -> rusqlite::Result<()> {
+    if !has_column(tx, "clipboard_entries", "content_hash")? {
+        tx.execute_batch("ALTER TABLE clipboard_entries ADD COLUMN content_hash TEXT;")?;
+    }
+
+    let rows: Vec<(i64, String, Vec<u8>, i64, i64, i64)> = {
+        let mut stmt = tx.prepare(
+            "SELECT id, content_type, COALESCE(CAST(text_content AS BLOB), image_data, X''),
+                    created_at, copy_count, first_copied_at
+             FROM clipboard_entries
+             WHERE content_hash IS NULL
+             ORDER BY created_at DESC, id DESC"
+        )?;
+        stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+        })?.collect::<Result<Vec<_>, _>>()?
+    };
+
+    for (id, content_type, data, created_at, copy_count, first_copied_at) in rows {
+        let hash = content_hash(&content_type, &data);
+        let existing: Option<i64> = tx.query_row(
+            "SELECT id FROM clipboard_entries WHERE content_hash = ?1",
+            params![hash],
+            |row| row.get(0),
+        ).optional()?;
+
+        match existing {
+            Some(keep_id) => {
+                tx.execute(
+                    "UPDATE clipboard_entries
+                     SET copy_count = copy_count + ?1,
+                         created_at = MAX(created_at, ?2),
+                         first_copied_at = MIN(first_copied_at, ?3)
+                     WHERE id = ?4",
+                    params![copy_count, created_at, first_copied_at, keep_id],
+                )?;
+                tx.execute("DELETE FROM clipboard_entries WHERE id = ?1", params![id])?;
+            }
+            None => {
+                tx.execute(
+                    "UPDATE clipboard_entries SET content_hash = ?1 WHERE id = ?2",
+                    params![hash, id],
+                )?;
+            }
+        }
+    }
+
+    tx.execute_batch(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_content_hash
+         ON clipboard_entries(content_hash);"
+    )
+}
+
+fn add_pinned(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+         ALTER TABLE clipboard_entries ADD COLUMN pin_order INTEGER;
+         CREATE INDEX IF NOT EXISTS idx_pinned
+         ON clipboard_entries(pinned, pin_order);"
+    )
+}
+
+fn create_tags(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS tags (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            name        TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            created_at  INTEGER NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS entry_tags (
+            entry_id    INTEGER NOT NULL REFERENCES clipboard_entries(id) ON DELETE CASCADE,
+            tag_id      INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+            PRIMARY KEY (entry_id, tag_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_entry_tags_tag
+        ON entry_tags(tag_id, entry_id);"
+    )
+}
+
+/// Trigram index used for CJK search terms. `unicode61` treats a run of
+/// kanji / kana / hangul as a single token, so words inside a sentence are
+/// only reachable by substring matching.
+fn create_trigram_fts(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS clipboard_fts_trigram
+        USING fts5(text_content, content='clipboard_entries', content_rowid='id', tokenize='trigram');
+
+        CREATE TRIGGER IF NOT EXISTS clipboard_entries_ai_trigram
+        AFTER INSERT ON clipboard_entries
+        BEGIN
+            INSERT INTO clipboard_fts_trigram(rowid, text_content)
+            VALUES (new.id, new.text_content);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS clipboard_entries_ad_trigram
+        AFTER DELETE ON clipboard_entries
+        BEGIN
+            INSERT INTO clipboard_fts_trigram(clipboard_fts_trigram, rowid, text_content)
+            VALUES ('delete', old.id, old.text_content);
+        END;
+
+        INSERT INTO clipboard_fts_trigram(clipboard_fts_trigram) VALUES ('rebuild');"
+    )
+}
+
+/// Indexes for `Storage::list_entries` and the `type:` / `app:` search filters.
+fn add_filter_indexes(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_content_type
+        ON clipboard_entries(content_type, created_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_source_app
+        ON clipboard_entries(source_app COLLATE NOCASE, created_at DESC);"
+    )
+}
+
+/// Creates `thumbnails` and renders one for every existing image entry.
+/// Images that cannot be decoded are left without a thumbnail.
+fn create_thumbnails(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS thumbnails (
+            entry_id  INTEGER PRIMARY KEY REFERENCES clipboard_entries(id) ON DELETE CASCADE,
+            width     INTEGER NOT NULL,
+            height    INTEGER NOT NULL,
+            format    TEXT NOT NULL,
+            data      BLOB NOT NULL
+        );"
+    )?;
+
+    let mut stmt = tx.prepare("SELECT id, image_data FROM clipboard_entries WHERE image_data IS NOT NULL")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get(0)?;
+        let data: Vec<u8> = row.get(1)?;
+        if let Ok(thumb) = thumbnail::generate(&data, thumbnail::STORED_MAX_PX) {
+            tx.execute(
+                "INSERT OR REPLACE INTO thumbnails (entry_id, width, height, format, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![id, thumb.width, thumb.height, thumb.format, thumb.png],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates the blob store, moves every inline image into it and drops
+/// `image_data`, which rewrites `clipboard_entries` without the image pages.
+fn move_images_to_blobs(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN blob_hash TEXT REFERENCES blobs(hash);
+         CREATE INDEX IF NOT EXISTS idx_blob_hash ON clipboard_entries(blob_hash);"
+    )?;
+    tx.execute_batch(blobs::SCHEMA)?;
+
+    let now = now_millis();
+    let mut stmt = tx.prepare("SELECT id, image_data FROM clipboard_entries WHERE image_data IS NOT NULL")?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let id: i64 = row.get(0)?;
+        let data: Vec<u8> = row.get(1)?;
+        let hash = blobs::put(tx, &data, now)?;
+        tx.execute("UPDATE clipboard_entries SET blob_hash = ?1 WHERE id = ?2", params![hash, id])?;
+    }
+
+    tx.execute_batch("ALTER TABLE clipboard_entries DROP COLUMN image_data;")
+}
+
+/// Detection results from `sensitive` rules (a JSON array of rule names) and
+/// the deletion deadline set by `Ttl` rules. Existing entries are not rescanned.
+/// `hash_key` holds one row with the random key for `keyed_content_hash`;
+/// Storage creates it on first use rather than here, where randomness could
+/// fail.
+fn add_sensitive(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE clipboard_entries ADD COLUMN sensitive TEXT;
+         ALTER TABLE clipboard_entries ADD COLUMN expires_at INTEGER;
+         CREATE INDEX IF NOT EXISTS idx_expires_at
+         ON clipboard_entries(expires_at) WHERE expires_at IS NOT NULL;
+         CREATE TABLE IF NOT EXISTS hash_key (
+             id   INTEGER PRIMARY KEY CHECK (id = 1),
+             key  BLOB NOT NULL
+         );"
+    )
+}
+
+fn create_ignored_apps(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS ignored_apps (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind        TEXT NOT NULL,
+            pattern     TEXT NOT NULL COLLATE NOCASE,
+            created_at  INTEGER NOT NULL,
+            UNIQUE (kind, pattern)
+        );"
+    )
+}
+
+/// One row holding the rule set as a JSON array; no row means the built-in
+/// rules, so databases that never changed them pick up new built-ins.
+fn create_sensitive_rules(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS sensitive_rules (
+            id          INTEGER PRIMARY KEY CHECK (id = 1),
+            rules       TEXT NOT NULL,
+            updated_at  INTEGER NOT NULL
+        );"
+    )
+}
+
+/// Lets `purge_ignored_app_entries` apply `BundleId` rules. Existing entries
+/// stay NULL; only the app name was recorded for them.
+fn add_source_bundle_id(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch("ALTER TABLE clipboard_entries ADD COLUMN source_bundle_id TEXT;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ContentType;
+    use crate::storage::Storage;
+    use std::path::PathBuf;
+
+    // Schema as shipped in the MVP: no copy tracking, no FTS, second timestamps.
+    const SHAPE_MVP: &str =
+        "CREATE TABLE clipboard_entries (
+            id            INTEGER PRIMARY KEY AUTOINCREMENT,
+            content_type  TEXT NOT NULL,
+            text_content  TEXT,
+            image_data    BLOB,
+            source_app    TEXT,
+            created_at    INTEGER NOT NULL
+        );
+        CREATE INDEX idx_created_at ON clipboard_entries(created_at DESC);
+        INSERT INTO clipboard_entries (content_type, text_content, source_app, created_at)
+        VALUES ('PlainText', 'mvp entry', 'App', 1700000000);
+        INSERT INTO clipboard_entries (content_type, image_data, source_app, created_at)
+        VALUES ('Image', X'FFD8', 'Preview', 1700000001);";
+
+    // Phase 2: FTS5 table and triggers added, still second timestamps.
+    const SHAPE_FTS: &str =
+        "CREATE TABLE clipboard_entries (
+            id            INTEGER PRIMARY KEY AUTOINCREMENT,
+            content_type  TEXT NOT NULL,
+            text_content  TEXT,
+            image_data    BLOB,
+            source_app    TEXT,
+            created_at    INTEGER NOT NULL
+        );
+        CREATE INDEX idx_created_at ON clipboard_entries(created_at DESC);
+        INSERT INTO clipboard_entries (content_type, text_content, source_app, created_at)
+        VALUES ('PlainText', 'indexed before fts', 'App', 1700000000);
+        CREATE VIRTUAL TABLE clipboard_fts
+        USING fts5(text_content, content='clipboard_entries', content_rowid='id');
+        CREATE TRIGGER clipboard_entries_ai AFTER INSERT ON clipboard_entries BEGIN
+            INSERT INTO clipboard_fts(rowid, text_content) VALUES (new.id, new.text_content);
+        END;
+        CREATE TRIGGER clipboard_entries_ad AFTER DELETE ON clipboard_entries BEGIN
+            INSERT INTO clipboard_fts(clipboard_fts, rowid, text_content)
+            VALUES ('delete', old.id, old.text_content);
+        END;
+        INSERT INTO clipboard_entries (content_type, text_content, source_app, created_at)
+        VALUES ('PlainText', 'indexed after fts', 'App', 1700000001);";
+
+    // Phase 3: copy_count / first_copied_at added, second timestamps.
+    const SHAPE_COPY_COUNT: &str =
+        "CREATE TABLE clipboard_entries (
+            id            INTEGER PRIMARY KEY AUTOINCREMENT,
+            content_type  TEXT NOT NULL,
+            text_content  TEXT,
+            image_data    BLOB,
+            source_app    TEXT,
+            created_at    INTEGER NOT NULL,
+            copy_count    INTEGER NOT NULL DEFAULT 1,
+            first_copied_at INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX idx_created_at ON clipboard_entries(created_at DESC);
+        CREATE VIRTUAL TABLE clipboard_fts
+        USING fts5(text_content, content='clipboard_entries', content_rowid='id');
+        CREATE TRIGGER clipboard_entries_ai AFTER INSERT ON clipboard_entries BEGIN
+            INSERT INTO clipboard_fts(rowid, text_content) VALUES (new.id, new.text_content);
+        END;
+        CREATE TRIGGER clipboard_entries_ad AFTER DELETE ON clipboard_entries BEGIN
+            INSERT INTO clipboard_fts(clipboard_fts, rowid, text_content)
+            VALUES ('delete', old.id, old.text_content);
+        END;
+        INSERT INTO clipboard_entries (content_type, text_content, source_app, created_at, copy_count, first_copied_at)
+        VALUES ('PlainText', 'copied thrice', 'App', 1700000100, 3, 1700000000);";
+
+    // Millisecond timestamps with duplicate rows, before content_hash existed.
+    const SHAPE_MILLIS: &str =
+        "CREATE TABLE clipboard_entries (
+            id            INTEGER PRIMARY KEY AUTOINCREMENT,
+            content_type  TEXT NOT NULL,
+            text_content  TEXT,
+            image_data    BLOB,
+            source_app    TEXT,
+            created_at    INTEGER NOT NULL,
+            copy_count    INTEGER NOT NULL DEFAULT 1,
+            first_copied_at INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX idx_created_at ON clipboard_entries(created_at DESC);
+        CREATE VIRTUAL TABLE clipboard_fts
+        USING fts5(text_content, content='clipboard_entries', content_rowid='id');
+        CREATE TRIGGER clipboard_entries_ai AFTER INSERT ON clipboard_entries BEGIN
+            INSERT INTO clipboard_fts(rowid, text_content) VALUES (new.id, new.text_content);
+        END;
+        CREATE TRIGGER clipboard_entries_ad AFTER DELETE ON clipboard_entries BEGIN
+            INSERT INTO clipboard_fts(clipboard_fts, rowid, text_content)
+            VALUES ('delete', old.id, old.text_content);
+        END;
+        INSERT INTO clipboard_entries (content_type, text_content, source_app, created_at, copy_count, first_copied_at)
+        VALUES ('PlainText', 'Dup', 'App', 1700000000000, 1, 1700000000000);
+        INSERT INTO clipboard_entries (content_type, text_content, source_app, created_at, copy_count, first_copied_at)
+        VALUES ('PlainText', 'Unique', 'App', 1700000000001, 1, 1700000000001);
+        INSERT INTO clipboard_entries (content_type, text_content, source_app, created_at, copy_count, first_copied_at)
+        VALUES ('PlainText', 'Dup', 'App', 1700000000002, 2, 1700000000002);";
+
+    fn fixture(name: &str, sql: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("cb_test_migrations_{name}"));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("clipboard.db");
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(sql).unwrap();
+        (dir, db_path)
+    }
+
+    fn version_of(db_path: &PathBuf) -> i64 {
+        schema_version(&Connection::open(db_path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_fresh_database_reaches_latest_version() {
+        let (dir, db_path) = fixture("fresh", "");
+        Storage::new(db_path.to_str().unwrap(), None).unwrap();
+        assert_eq!(version_of(&db_path), LATEST_VERSION);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_migrate_mvp_shape() {
+        let (dir, db_path) = fixture("mvp", SHAPE_MVP);
+        {
+            let storage = Storage::new(db_path.to_str().unwrap(), None).unwrap();
+            let entries = storage.get_recent_entries(10).unwrap();
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].created_at, 1_700_000_001_000);
+            assert_eq!(entries[1].created_at, 1_700_000_000_000);
+            assert_eq!(entries[1].copy_count, 1);
+            assert_eq!(entries[1].first_copied_at, 1_700_000_000_000);
+
+            let results = storage.search_entries("mvp", 10).unwrap();
+            assert_eq!(results.len(), 1);
+
+            let image = storage.get_entry_image(entries[0].id).unwrap();
+            assert_eq!(image.as_deref(), Some([0xFF, 0xD8].as_slice()));
+        }
+        assert_eq!(version_of(&db_path), LATEST_VERSION);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_migrate_fts_shape_rebuilds_index() {
+        let (dir, db_path) = fixture("fts", SHAPE_FTS);
+        let storage = Storage::new(db_path.to_str().unwrap(), None).unwrap();
+        // The row inserted before the FTS table existed must be searchable
+        let results = storage.search_entries("indexed", 10).unwrap();
+        assert_eq!(results.len(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_migrate_copy_count_shape() {
+        let (dir, db_path) = fixture("copy_count", SHAPE_COPY_COUNT);
+        let storage = Storage::new(db_path.to_str().unwrap(), None).unwrap();
+        let entries = storage.get_recent_entries(10).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].copy_count, 3);
+        assert_eq!(entries[0].created_at, 1_700_000_100_000);
+        assert_eq!(entries[0].first_copied_at, 1_700_000_000_000);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_migrate_millis_shape_merges_duplicates() {
+        let (dir, db_path) = fixture("millis", SHAPE_MILLIS);
+        let storage = Storage::new(db_path.to_str().unwrap(), None).unwrap();
+
+        let entries = storage.get_recent_entries(10).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].text_content.as_deref(), Some("Dup"));
+        assert_eq!(entries[0].copy_count, 3);
+        assert_eq!(entries[0].created_at, 1_700_000_000_002);
+        assert_eq!(entries[0].first_copied_at, 1_700_000_000_000);
+
+        let results = storage.search_entries("Dup", 10).unwrap();
+        assert_eq!(results.len(), 1);
+
+        // New inserts merge into the backfilled row
+        let outcome = storage.insert_text_entry(&ContentType::PlainText, "Dup", "App").unwrap();
+        assert_eq!(outcome.id(), Some(entries[0].id));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_migrations_run_once() {
+        let (dir, db_path) = fixture("run_once", "");
+        Storage::new(db_path.to_str().unwrap(), None).unwrap();
+
+        // A small timestamp written after migration must not be rescaled on reopen
+        Connection::open(&db_path).unwrap().execute(
+            "INSERT INTO clipboard_entries (content_type, text_content, source_app, created_at, first_copied_at)
+             VALUES ('PlainText', 'tiny', 'App', 5, 5)",
+            [],
+        ).unwrap();
+
+        let storage = Storage::new(db_path.to_str().unwrap(), None).unwrap();
+        let entries = storage.get_recent_entries(10).unwrap();
+        assert_eq!(entries[0].created_at, 5);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_refuses_newer_schema_version() {
+        let (dir, db_path) = fixture("too_new", "");
+        Storage::new(db_path.to_str().unwrap(), None).unwrap();
+        Connection::open(&db_path).unwrap()
+            .pragma_update(None, "user_version", LATEST_VERSION + 1)
+            .unwrap();
+
+        let result = Storage::new(db_path.to_str().unwrap(), None);
+        assert!(matches!(result, Err(Error::UnsupportedSchema { .. })));
+        assert_eq!(version_of(&db_path), LATEST_VERSION + 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// An in-memory database with migrations up to `version` applied.
+    fn migrated_to(version: i64) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= version) {
+            let tx = conn.unchecked_transaction().unwrap();
+            (migration.up)(&tx).unwrap();
+            tx.pragma_update(None, "user_version", migration.version).unwrap();
+            tx.commit().unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn test_trigram_index_rebuilt_for_existing_rows() {
+        let conn = migrated_to(8);
+        conn.execute(
+            "INSERT INTO clipboard_entries (content_type, text_content, source_app, created_at, first_copied_at)
+             VALUES ('PlainText', '東京都庁の住所を調べる', 'App', 1, 1)",
+            [],
+        ).unwrap();
+
+        run(&conn).unwrap();
+        let hits: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM clipboard_fts_trigram WHERE clipboard_fts_trigram MATCH '\"都庁の\"'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(hits, 1);
+    }
+
+    #[test]
+    fn test_thumbnails_backfilled_for_existing_images() {
+        let conn = migrated_to(10);
+        let png = thumbnail::tests::sample_image(600, 300, image::ImageFormat::Png);
+        conn.execute(
+            "INSERT INTO clipboard_entries (content_type, image_data, source_app, created_at, first_copied_at)
+             VALUES ('Image', ?1, 'App', 1, 1), ('Image', X'0102', 'App', 2, 2)",
+            params![png],
+        ).unwrap();
+
+        run(&conn).unwrap();
+        let rows: Vec<(i64, i64, String)> = conn.prepare("SELECT width, height, format FROM thumbnails")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows, vec![(600, 300, "png".to_string())]);
+    }
+
+    #[test]
+    fn test_inline_images_moved_to_blobs() {
+        let conn = migrated_to(11);
+        conn.execute_batch(
+            "INSERT INTO clipboard_entries (content_type, image_data, source_app, created_at, first_copied_at)
+             VALUES ('Image', X'FFD8FF', 'App', 1, 1);
+             INSERT INTO clipboard_entries (content_type, text_content, source_app, created_at, first_copied_at)
+             VALUES ('PlainText', 'text', 'App', 2, 2);"
+        ).unwrap();
+
+        run(&conn).unwrap();
+        let (hash, refcount, data): (String, i64, Vec<u8>) = conn.query_row(
+            "SELECT b.hash, b.refcount, b.data FROM clipboard_entries e JOIN blobs b ON b.hash = e.blob_hash",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap();
+        assert_eq!(hash, blobs::hash(&[0xFF, 0xD8, 0xFF]));
+        assert_eq!(refcount, 1);
+        assert_eq!(data, vec![0xFF, 0xD8, 0xFF]);
+
+        let has_image_data: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('clipboard_entries') WHERE name = 'image_data')",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert!(!has_image_data);
+    }
+
+    #[test]
+    fn test_failed_migration_rolls_back() {
+        let conn = Connection::open_in_memory().unwrap();
+        // A conflicting object makes migration 1 fail midway
+        conn.execute_batch("CREATE VIEW clipboard_entries AS SELECT 1;").unwrap();
+        assert!(run(&conn).is_err());
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+    }
+}
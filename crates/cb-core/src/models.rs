@@ -1,6 +1,10 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ContentType {
     PlainText,
     RichText,
@@ -18,6 +22,7 @@ impl ContentType {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         match s {
             "PlainText" => ContentType::PlainText,
@@ -29,6 +34,100 @@ impl ContentType {
     }
 }
 
+/// Criteria for `Storage::list_entries`. Every field is optional in JSON;
+/// empty lists and `None` do not constrain the result.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntryFilter {
+    /// Any of these types, by `ContentType` name (e.g. `"Image"`).
+    pub content_types: Vec<ContentType>,
+    /// Any of these source apps, compared case-insensitively.
+    pub source_apps: Vec<String>,
+    /// `created_at >= since`, in milliseconds.
+    pub since: Option<i64>,
+    /// `created_at < until`, in milliseconds.
+    pub until: Option<i64>,
+    pub min_copy_count: Option<i64>,
+    pub has_text: Option<bool>,
+    pub has_image: Option<bool>,
+}
+
+/// Position in the `(created_at, id)` ordering used for keyset pagination.
+/// Clients only see it as the opaque string from `encode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: i64,
+    pub id: i64,
+}
+
+impl Cursor {
+    const PREFIX: &'static str = "c1";
+
+    pub fn encode(&self) -> String {
+        format!("{}.{}.{}", Self::PREFIX, self.created_at, self.id)
+    }
+
+    pub fn decode(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidInput(format!("malformed cursor: {s}"));
+        let mut parts = s.split('.');
+        if parts.next() != Some(Self::PREFIX) {
+            return Err(invalid());
+        }
+        let mut next = || parts.next().and_then(|p| p.parse::<i64>().ok()).ok_or_else(invalid);
+        let cursor = Cursor { created_at: next()?, id: next()? };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(cursor)
+    }
+}
+
+/// Which way to page from a cursor. `Older` scrolls down the list (toward
+/// older entries), `Newer` scrolls back up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageDirection {
+    Older,
+    Newer,
+}
+
+impl PageDirection {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "Newer" => PageDirection::Newer,
+            _ => PageDirection::Older,
+        }
+    }
+}
+
+/// One page of results. Pass `next_cursor` with `Older`, or `prev_cursor`
+/// with `Newer`, to fetch the adjacent page; `None` means there is none.
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+}
+
+/// Result ordering for `Storage::search_entries_ranked`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SortOrder {
+    /// Newest first, as in `search_entries`.
+    Recent,
+    /// FTS5 bm25 weighted by recency and copy count.
+    Relevance,
+}
+
+impl SortOrder {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "Relevance" => SortOrder::Relevance,
+            _ => SortOrder::Recent,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClipboardEntry {
     pub id: i64,
@@ -37,7 +136,120 @@ pub struct ClipboardEntry {
     #[serde(skip)]
     pub image_data: Option<Vec<u8>>,
     pub source_app: Option<String>,
+    /// Bundle id of the source app, when the copy recorded one.
+    pub source_bundle_id: Option<String>,
     pub created_at: i64,
     pub copy_count: i64,
     pub first_copied_at: i64,
+    pub pinned: bool,
+    pub pin_order: Option<i64>,
+    pub tags: Vec<String>,
+    /// Original pixel size of decodable image entries.
+    pub image_width: Option<u32>,
+    pub image_height: Option<u32>,
+    /// Hash of the blob holding an image entry's bytes.
+    pub blob_hash: Option<String>,
+    /// Names of the `sensitive` rules that fired when the text was saved.
+    pub sensitive: Vec<String>,
+    /// When the entry is deleted, in milliseconds; set by `Ttl` rules, TTL
+    /// inserts and `set_entry_expiry`. Always `None` for pinned entries.
+    pub expires_at: Option<i64>,
+}
+
+/// What `Storage::insert_text_entry` / `insert_image_entry` did with a copy.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SaveOutcome {
+    /// Stored as a new entry, or merged into an identical one.
+    Stored { id: i64 },
+    /// Not stored because a `sensitive` rule with the `Skip` action fired.
+    Skipped { rule: String },
+    /// Not stored because the source app matched an ignore rule.
+    Ignored { pattern: String },
+}
+
+impl SaveOutcome {
+    /// The entry id, unless nothing was stored.
+    pub fn id(&self) -> Option<i64> {
+        match self {
+            SaveOutcome::Stored { id } => Some(*id),
+            SaveOutcome::Skipped { .. } | SaveOutcome::Ignored { .. } => None,
+        }
+    }
+}
+
+/// How far `Storage::backup_to` / `restore_from` got, in database pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BackupProgress {
+    pub pages_done: i64,
+    pub pages_total: i64,
+}
+
+/// Totals over unexpired entries, from `Storage::stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HistoryStats {
+    pub entries: u64,
+    pub pinned: u64,
+    /// Entry counts keyed by `ContentType` name.
+    pub by_type: BTreeMap<String, u64>,
+    pub tags: u64,
+    /// UTF-8 bytes of `text_content`.
+    pub text_bytes: u64,
+    /// Bytes in the blob store, counting shared images once.
+    pub image_bytes: u64,
+    pub oldest_at: Option<i64>,
+    pub newest_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    pub entry_count: i64,
+}
+
+/// A half-open range `[start, end)` in UTF-16 code units, matching `NSRange`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Result of fuzzy matching an entry against the query words.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FuzzyMatch {
+    /// Higher is better; only comparable within one query.
+    pub score: i64,
+    /// UTF-16 offsets of the matched characters in `text_content`.
+    pub positions: Vec<usize>,
+}
+
+/// A search result with the matching region located by FTS5, or by
+/// substring search for CJK terms. Fields are empty when the query had no
+/// text terms.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub entry: ClipboardEntry,
+    /// Short excerpt around the best match, from FTS5 `snippet()`.
+    pub snippet: Option<String>,
+    /// Matched terms within `snippet`.
+    pub snippet_ranges: Vec<MatchRange>,
+    /// Matched terms within `entry.text_content`, from FTS5 `highlight()`
+    /// and CJK substring positions.
+    pub match_ranges: Vec<MatchRange>,
+    /// Set when the hit came from the fuzzy fallback rather than FTS5.
+    pub fuzzy: Option<FuzzyMatch>,
+}
+
+impl From<ClipboardEntry> for SearchHit {
+    fn from(entry: ClipboardEntry) -> Self {
+        SearchHit {
+            entry,
+            snippet: None,
+            snippet_ranges: Vec::new(),
+            match_ranges: Vec::new(),
+            fuzzy: None,
+        }
+    }
 }
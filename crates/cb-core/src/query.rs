@@ -0,0 +1,779 @@
+//! Search query language for `Storage::search_entries`.
+//!
+//! ```text
+//! kubectl "exact phrase" app:Xcode type:filepath -draft after:2026-09-01
+//! (error OR warning) copies:>=3 is:pinned tag:sql
+//! ```
+//!
+//! Bare words are FTS5 prefix terms, quoted text is an exact phrase, `-`
+//! negates the following term or group, `OR` joins alternatives and
+//! adjacent terms are ANDed. Terms containing CJK characters are matched as
+//! substrings through the trigram index (or `LIKE` below three characters),
+//! since `unicode61` does not split kanji, kana or hangul into words.
+//! Field filters:
+//!
+//! | Field | Example | Meaning |
+//! |-------|---------|---------|
+//! | `app:` | `app:"Google Chrome"` | `source_app` equals (case-insensitive) |
+//! | `type:` | `type:image` | `text`, `rich`, `image`, `file` (or the `ContentType` name) |
+//! | `after:` / `before:` | `after:2026-09-01` | `created_at` on/after, or before, the UTC date |
+//! | `copies:` | `copies:>=3` | `copy_count` compared with `=`, `>`, `>=`, `<`, `<=` |
+//! | `is:` | `is:pinned`, `is:tagged` | pin / tag state |
+//! | `tag:` | `tag:sql` | carries the tag (case-insensitive) |
+//!
+//! Tokens that look like a field but are not recognised (e.g. `http://…`, or
+//! `after:` with an invalid date) are searched as text. Every user-supplied
+//! value is bound as a parameter; FTS terms are always emitted as quoted
+//! FTS5 strings, so FTS5 operators cannot be injected either.
+
+use rusqlite::types::Value;
+use crate::models::{ContentType, MatchRange};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Text { text: String, prefix: bool },
+    Filter(Filter),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    App(String),
+    Type(ContentType),
+    After(i64),
+    Before(i64),
+    Copies(Comparison, i64),
+    Pinned,
+    Tagged,
+    Tag(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+impl Comparison {
+    fn as_sql(self) -> &'static str {
+        match self {
+            Comparison::Eq => "=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+        }
+    }
+}
+
+/// A query compiled against `clipboard_entries` aliased as `e`.
+#[derive(Debug, Default)]
+pub struct CompiledQuery {
+    /// FTS5 MATCH expression for the top-level positive text terms. When
+    /// present, callers join `clipboard_fts` and bind this first.
+    pub match_expr: Option<String>,
+    /// Boolean SQL expression for everything else; `1` when empty.
+    pub where_sql: String,
+    pub params: Vec<Value>,
+    /// Top-level positive CJK terms. They are matched in `where_sql`, out of
+    /// FTS5's reach, so callers highlight and rank them with
+    /// `substring_ranges` and `substring_bm25`.
+    pub substring_terms: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Or,
+    Not,
+    Phrase(String),
+    Word(String),
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::LParen);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::RParen);
+        } else if c == '-' {
+            chars.next();
+            // A lone or trailing `-` is ignored rather than negating nothing
+            if chars.peek().is_some_and(|n| !n.is_whitespace() && *n != ')') {
+                tokens.push(Token::Not);
+            }
+        } else if c == '"' {
+            chars.next();
+            let mut phrase = String::new();
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                phrase.push(c);
+            }
+            tokens.push(Token::Phrase(phrase));
+        } else {
+            // Words run to whitespace or a paren; quotes inside a word wrap a
+            // value that may contain spaces, e.g. app:"Google Chrome".
+            let mut word = String::new();
+            let mut quoted = false;
+            while let Some(&c) = chars.peek() {
+                if c == '"' {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if c == '"' {
+                            break;
+                        }
+                        word.push(c);
+                    }
+                    quoted = true;
+                } else if c.is_whitespace() || c == '(' || c == ')' {
+                    break;
+                } else {
+                    chars.next();
+                    word.push(c);
+                }
+            }
+            if word == "OR" && !quoted {
+                tokens.push(Token::Or);
+            } else {
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Option<Expr> {
+        let mut alternatives = Vec::new();
+        if let Some(e) = self.parse_and() {
+            alternatives.push(e);
+        }
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            if let Some(e) = self.parse_and() {
+                alternatives.push(e);
+            }
+        }
+        simplify(alternatives, Expr::Or)
+    }
+
+    fn parse_and(&mut self) -> Option<Expr> {
+        let mut terms = Vec::new();
+        while let Some(token) = self.peek() {
+            if matches!(token, Token::RParen | Token::Or) {
+                break;
+            }
+            if let Some(e) = self.parse_unary() {
+                terms.push(e);
+            }
+        }
+        simplify(terms, Expr::And)
+    }
+
+    fn parse_unary(&mut self) -> Option<Expr> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            return self.parse_unary().map(|e| Expr::Not(Box::new(e)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Option<Expr> {
+        match self.next()? {
+            Token::LParen => {
+                let inner = self.parse_or();
+                if self.peek() == Some(&Token::RParen) {
+                    self.next();
+                }
+                inner
+            }
+            Token::Phrase(phrase) => text_term(&phrase, false),
+            Token::Word(word) => parse_filter(&word).map(Expr::Filter).or_else(|| {
+                let stripped = word.trim_end_matches('*');
+                text_term(stripped, true)
+            }),
+            Token::Not | Token::Or | Token::RParen => None,
+        }
+    }
+}
+
+fn simplify(mut items: Vec<Expr>, combine: fn(Vec<Expr>) -> Expr) -> Option<Expr> {
+    match items.len() {
+        0 => None,
+        1 => items.pop(),
+        _ => Some(combine(items)),
+    }
+}
+
+fn text_term(text: &str, prefix: bool) -> Option<Expr> {
+    let text = text.trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(Expr::Text { text: text.to_string(), prefix })
+    }
+}
+
+fn parse_filter(word: &str) -> Option<Filter> {
+    let (field, value) = word.split_once(':')?;
+    if value.is_empty() {
+        return None;
+    }
+    match field.to_ascii_lowercase().as_str() {
+        "app" => Some(Filter::App(value.to_string())),
+        "type" => parse_content_type(value).map(Filter::Type),
+        "after" => parse_date(value).map(Filter::After),
+        "before" => parse_date(value).map(Filter::Before),
+        "copies" => parse_comparison(value).map(|(op, n)| Filter::Copies(op, n)),
+        "is" => match value.to_ascii_lowercase().as_str() {
+            "pinned" => Some(Filter::Pinned),
+            "tagged" => Some(Filter::Tagged),
+            _ => None,
+        },
+        "tag" => Some(Filter::Tag(value.to_string())),
+        _ => None,
+    }
+}
+
+fn parse_content_type(value: &str) -> Option<ContentType> {
+    match value.to_ascii_lowercase().as_str() {
+        "text" | "plain" | "plaintext" => Some(ContentType::PlainText),
+        "rich" | "richtext" => Some(ContentType::RichText),
+        "image" | "img" => Some(ContentType::Image),
+        "file" | "filepath" | "path" => Some(ContentType::FilePath),
+        _ => None,
+    }
+}
+
+fn parse_comparison(value: &str) -> Option<(Comparison, i64)> {
+    let (op, number) = if let Some(rest) = value.strip_prefix(">=") {
+        (Comparison::Ge, rest)
+    } else if let Some(rest) = value.strip_prefix("<=") {
+        (Comparison::Le, rest)
+    } else if let Some(rest) = value.strip_prefix('>') {
+        (Comparison::Gt, rest)
+    } else if let Some(rest) = value.strip_prefix('<') {
+        (Comparison::Lt, rest)
+    } else {
+        (Comparison::Eq, value.strip_prefix('=').unwrap_or(value))
+    };
+    number.parse().ok().map(|n| (op, n))
+}
+
+/// Parses `YYYY-MM-DD` into a millisecond timestamp at 00:00 UTC. Years
+/// outside 0..=9999 and days past the end of the month are rejected.
+pub fn parse_date(value: &str) -> Option<i64> {
+    let mut parts = value.splitn(3, '-');
+    let year = parse_digits(parts.next()?)?;
+    let month = parse_digits(parts.next()?)?;
+    let day = parse_digits(parts.next()?)?;
+    if !(0..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    days_from_civil(year, month, day).checked_mul(86_400_000)
+}
+
+/// Unsigned decimal digits only; `str::parse` would also take `+3`.
+fn parse_digits(s: &str) -> Option<i64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Howard Hinnant's days_from_civil: days since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Parses a query string. Returns `None` when it contains no usable terms.
+/// Parsing never fails: unbalanced parentheses and quotes are closed implicitly.
+pub fn parse(input: &str) -> Option<Expr> {
+    let mut parser = Parser { tokens: tokenize(input), pos: 0 };
+    let mut groups = Vec::new();
+    while parser.peek().is_some() {
+        if let Some(e) = parser.parse_or() {
+            groups.push(e);
+        }
+        // Skip a stray closing paren and keep going
+        if parser.peek() == Some(&Token::RParen) {
+            parser.next();
+        }
+    }
+    simplify(groups, Expr::And)
+}
+
+/// Quotes a term as an FTS5 string so its content is never parsed as syntax.
+pub fn fts_term(text: &str, prefix: bool) -> String {
+    let quoted = format!("\"{}\"", text.replace('"', "\"\""));
+    if prefix { quoted + "*" } else { quoted }
+}
+
+pub fn compile(expr: &Expr) -> CompiledQuery {
+    let mut top_level = Vec::new();
+    flatten_and(expr, &mut top_level);
+
+    let mut match_terms = Vec::new();
+    let mut substring_terms = Vec::new();
+    let mut conditions = Vec::new();
+    let mut params = Vec::new();
+    for item in top_level {
+        match item {
+            Expr::Text { text, prefix } if !is_cjk(text) => match_terms.push(fts_term(text, *prefix)),
+            Expr::Text { text, .. } => {
+                substring_terms.push(text.clone());
+                conditions.push(compile_cjk_term(text, &mut params));
+            }
+            other => conditions.push(compile_expr(other, &mut params)),
+        }
+    }
+
+    CompiledQuery {
+        match_expr: if match_terms.is_empty() { None } else { Some(match_terms.join(" AND ")) },
+        where_sql: if conditions.is_empty() { "1".to_string() } else { conditions.join(" AND ") },
+        params,
+        substring_terms,
+    }
+}
+
+/// Splits off the top-level positive text terms, returning them and the
+/// remaining expression (filters, negations, OR groups), if any.
+pub fn split_text_terms(expr: &Expr) -> (Vec<String>, Option<Expr>) {
+    let mut top_level = Vec::new();
+    flatten_and(expr, &mut top_level);
+
+    let mut terms = Vec::new();
+    let mut rest = Vec::new();
+    for item in top_level {
+        match item {
+            Expr::Text { text, .. } => terms.push(text.clone()),
+            other => rest.push(other.clone()),
+        }
+    }
+    (terms, simplify(rest, Expr::And))
+}
+
+fn flatten_and<'a>(expr: &'a Expr, out: &mut Vec<&'a Expr>) {
+    match expr {
+        Expr::And(items) => items.iter().for_each(|e| flatten_and(e, out)),
+        other => out.push(other),
+    }
+}
+
+fn compile_expr(expr: &Expr, params: &mut Vec<Value>) -> String {
+    match expr {
+        Expr::And(items) => join(items, " AND ", params),
+        Expr::Or(items) => join(items, " OR ", params),
+        Expr::Not(inner) => format!("NOT ({})", compile_expr(inner, params)),
+        Expr::Text { text, .. } if is_cjk(text) => compile_cjk_term(text, params),
+        Expr::Text { text, prefix } => {
+            params.push(Value::Text(fts_term(text, *prefix)));
+            "e.id IN (SELECT rowid FROM clipboard_fts WHERE clipboard_fts MATCH ?)".to_string()
+        }
+        Expr::Filter(filter) => compile_filter(filter, params),
+    }
+}
+
+/// True if the term contains Han, kana or hangul, which `unicode61` cannot
+/// split into words.
+fn is_cjk(text: &str) -> bool {
+    text.chars().any(|c| matches!(c,
+        '\u{3040}'..='\u{30FF}'     // Hiragana, Katakana
+        | '\u{31F0}'..='\u{31FF}'   // Katakana phonetic extensions
+        | '\u{3400}'..='\u{4DBF}'   // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK Unified Ideographs
+        | '\u{F900}'..='\u{FAFF}'   // CJK Compatibility Ideographs
+        | '\u{FF66}'..='\u{FF9F}'   // Halfwidth Katakana
+        | '\u{1100}'..='\u{11FF}'   // Hangul Jamo
+        | '\u{3130}'..='\u{318F}'   // Hangul Compatibility Jamo
+        | '\u{AC00}'..='\u{D7AF}'   // Hangul Syllables
+        | '\u{20000}'..='\u{2FA1F}' // CJK Extensions B-F, Compatibility Supplement
+    ))
+}
+
+/// Substring match for a CJK term: the trigram index when the term has at
+/// least three characters, otherwise a `LIKE` scan, since trigram queries
+/// cannot match anything shorter.
+fn compile_cjk_term(text: &str, params: &mut Vec<Value>) -> String {
+    if text.chars().count() >= 3 {
+        params.push(Value::Text(fts_term(text, false)));
+        "e.id IN (SELECT rowid FROM clipboard_fts_trigram WHERE clipboard_fts_trigram MATCH ?)".to_string()
+    } else {
+        let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        params.push(Value::Text(format!("%{escaped}%")));
+        "COALESCE(e.text_content, '') LIKE ? ESCAPE '\\'".to_string()
+    }
+}
+
+fn join(items: &[Expr], separator: &str, params: &mut Vec<Value>) -> String {
+    let parts: Vec<String> = items.iter().map(|e| compile_expr(e, params)).collect();
+    format!("({})", parts.join(separator))
+}
+
+fn compile_filter(filter: &Filter, params: &mut Vec<Value>) -> String {
+    match filter {
+        Filter::App(app) => {
+            params.push(Value::Text(app.clone()));
+            "e.source_app = ? COLLATE NOCASE".to_string()
+        }
+        Filter::Type(content_type) => {
+            params.push(Value::Text(content_type.as_str().to_string()));
+            "e.content_type = ?".to_string()
+        }
+        Filter::After(ts) => {
+            params.push(Value::Integer(*ts));
+            "e.created_at >= ?".to_string()
+        }
+        Filter::Before(ts) => {
+            params.push(Value::Integer(*ts));
+            "e.created_at < ?".to_string()
+        }
+        Filter::Copies(op, n) => {
+            params.push(Value::Integer(*n));
+            format!("e.copy_count {} ?", op.as_sql())
+        }
+        Filter::Pinned => "e.pinned = 1".to_string(),
+        Filter::Tagged => "EXISTS (SELECT 1 FROM entry_tags et WHERE et.entry_id = e.id)".to_string(),
+        Filter::Tag(name) => {
+            params.push(Value::Text(name.clone()));
+            "EXISTS (SELECT 1 FROM entry_tags et JOIN tags t ON t.id = et.tag_id
+                     WHERE et.entry_id = e.id AND t.name = ?)".to_string()
+        }
+    }
+}
+
+/// Case-insensitive occurrences of `terms` in `text` as merged UTF-16
+/// ranges, like the ones FTS5 `highlight()` gives for other terms.
+pub fn substring_ranges(text: &str, terms: &[String]) -> Vec<MatchRange> {
+    let chars: Vec<(char, usize)> = text.chars()
+        .scan(0, |offset, c| {
+            let start = *offset;
+            *offset += c.len_utf16();
+            Some((fold_case(c), start))
+        })
+        .collect();
+    let end_of = |i: usize| chars.get(i).map_or(text.encode_utf16().count(), |&(_, offset)| offset);
+
+    let mut ranges = Vec::new();
+    for term in terms {
+        let term: Vec<char> = term.chars().map(fold_case).collect();
+        if term.is_empty() {
+            continue;
+        }
+        let mut i = 0;
+        while i + term.len() <= chars.len() {
+            if chars[i..i + term.len()].iter().map(|&(c, _)| c).eq(term.iter().copied()) {
+                ranges.push(MatchRange { start: chars[i].1, end: end_of(i + term.len()) });
+                i += term.len();
+            } else {
+                i += 1;
+            }
+        }
+    }
+    merge_ranges(ranges)
+}
+
+/// Sorts ranges and joins the ones that overlap or touch.
+pub fn merge_ranges(mut ranges: Vec<MatchRange>) -> Vec<MatchRange> {
+    ranges.sort_by_key(|range| (range.start, range.end));
+    let mut merged: Vec<MatchRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+fn fold_case(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// BM25 parameters for `substring_bm25`, FTS5's defaults.
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+/// Stands in for the average document length, which would need a scan of
+/// every entry.
+const BM25_REFERENCE_CHARS: f64 = 200.0;
+
+/// A `bm25()`-like value (negative, lower is better) for CJK substring
+/// terms, which FTS5 does not rank. Term frequency saturates and long texts
+/// are normalised as in BM25; IDF is left out since every hit contains
+/// every top-level term.
+pub fn substring_bm25(text: &str, terms: &[String]) -> f64 {
+    let length = text.chars().count() as f64;
+    let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * length / BM25_REFERENCE_CHARS);
+    let folded: String = text.chars().map(fold_case).collect();
+    -terms.iter()
+        .map(|term| {
+            let term: String = term.chars().map(fold_case).collect();
+            let frequency = if term.is_empty() { 0.0 } else { folded.matches(&term).count() as f64 };
+            frequency * (BM25_K1 + 1.0) / (frequency + norm)
+        })
+        .sum::<f64>()
+}
+
+/// Age at which an entry's relevance has halved.
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+const MILLIS_PER_DAY: f64 = 86_400_000.0;
+
+/// Score for relevance ordering; higher is better. `bm25` is the raw FTS5
+/// value (lower is better), or `None` when the query has no text terms.
+/// Recency decays hyperbolically so old strong matches still surface, and
+/// repeated copies add a logarithmic boost.
+pub fn relevance_score(bm25: Option<f64>, created_at: i64, copy_count: i64, now: i64) -> f64 {
+    let text = bm25.map_or(1.0, |b| -b);
+    let age_days = (now - created_at).max(0) as f64 / MILLIS_PER_DAY;
+    let recency = 1.0 / (1.0 + age_days / RECENCY_HALF_LIFE_DAYS);
+    let frequency = 1.0 + (copy_count.max(1) as f64).ln();
+    text * recency * frequency
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(t: &str) -> Expr {
+        Expr::Text { text: t.to_string(), prefix: true }
+    }
+
+    fn phrase(t: &str) -> Expr {
+        Expr::Text { text: t.to_string(), prefix: false }
+    }
+
+    #[test]
+    fn test_parse_words_and_phrase() {
+        assert_eq!(parse("kubectl"), Some(text("kubectl")));
+        assert_eq!(
+            parse("get \"exact phrase\" pods*"),
+            Some(Expr::And(vec![text("get"), phrase("exact phrase"), text("pods")]))
+        );
+    }
+
+    #[test]
+    fn test_parse_empty_queries() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("   "), None);
+        assert_eq!(parse("*"), None);
+        assert_eq!(parse("()"), None);
+        assert_eq!(parse("- \"\""), None);
+    }
+
+    #[test]
+    fn test_parse_field_filters() {
+        assert_eq!(
+            parse("app:\"Google Chrome\" type:filepath is:pinned tag:sql copies:>=3"),
+            Some(Expr::And(vec![
+                Expr::Filter(Filter::App("Google Chrome".to_string())),
+                Expr::Filter(Filter::Type(ContentType::FilePath)),
+                Expr::Filter(Filter::Pinned),
+                Expr::Filter(Filter::Tag("sql".to_string())),
+                Expr::Filter(Filter::Copies(Comparison::Ge, 3)),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_parse_dates() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2026-09-01"), Some(1_788_220_800_000));
+        assert_eq!(parse_date("2024-02-29"), Some(1_709_164_800_000));
+        assert_eq!(parse_date("2026-13-01"), None);
+        assert_eq!(parse_date("yesterday"), None);
+        assert_eq!(parse_date("2026-02-31"), None);
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2026-04-31"), None);
+        assert_eq!(parse_date("+2026-01-01"), None);
+        assert_eq!(parse_date("2026-+1-01"), None);
+        assert_eq!(parse_date("999999999999-01-01"), None);
+        assert_eq!(parse_date("10000-01-01"), None);
+        assert_eq!(parse_date("9999-12-31"), Some(253_402_214_400_000));
+        assert_eq!(parse("after:999999999999-01-01"), Some(text("after:999999999999-01-01")));
+        assert_eq!(parse("after:2026-09-01"), Some(Expr::Filter(Filter::After(1_788_220_800_000))));
+    }
+
+    #[test]
+    fn test_unknown_or_invalid_fields_are_text() {
+        assert_eq!(parse("http://example.com"), Some(text("http://example.com")));
+        assert_eq!(parse("after:someday"), Some(text("after:someday")));
+        assert_eq!(parse("type:video"), Some(text("type:video")));
+        assert_eq!(parse("app:"), Some(text("app:")));
+    }
+
+    #[test]
+    fn test_parse_negation_and_or_groups() {
+        assert_eq!(
+            parse("deploy -draft (error OR warning)"),
+            Some(Expr::And(vec![
+                text("deploy"),
+                Expr::Not(Box::new(text("draft"))),
+                Expr::Or(vec![text("error"), text("warning")]),
+            ]))
+        );
+        assert_eq!(
+            parse("a b OR c"),
+            Some(Expr::Or(vec![Expr::And(vec![text("a"), text("b")]), text("c")]))
+        );
+        // Lowercase "or" and quoted "OR" are plain terms
+        assert_eq!(parse("this or that"), Some(Expr::And(vec![text("this"), text("or"), text("that")])));
+        assert_eq!(parse("\"OR\""), Some(phrase("OR")));
+    }
+
+    #[test]
+    fn test_parse_unbalanced_input() {
+        assert_eq!(parse("(a OR b"), Some(Expr::Or(vec![text("a"), text("b")])));
+        assert_eq!(parse("a) b"), Some(Expr::And(vec![text("a"), text("b")])));
+        assert_eq!(parse("\"unterminated phrase"), Some(phrase("unterminated phrase")));
+        assert_eq!(parse("a -"), Some(text("a")));
+    }
+
+    #[test]
+    fn test_compile_splits_match_and_where() {
+        let compiled = compile(&parse("kubectl \"get pods\" app:Terminal -draft").unwrap());
+        assert_eq!(compiled.match_expr.as_deref(), Some("\"kubectl\"* AND \"get pods\""));
+        assert_eq!(
+            compiled.where_sql,
+            "e.source_app = ? COLLATE NOCASE AND NOT (e.id IN (SELECT rowid FROM clipboard_fts WHERE clipboard_fts MATCH ?))"
+        );
+        assert_eq!(compiled.params, vec![
+            Value::Text("Terminal".to_string()),
+            Value::Text("\"draft\"*".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_compile_filters_only() {
+        let compiled = compile(&parse("type:image").unwrap());
+        assert!(compiled.match_expr.is_none());
+        assert_eq!(compiled.where_sql, "e.content_type = ?");
+    }
+
+    #[test]
+    fn test_sql_injection_is_parameterized() {
+        let input = "x'; DROP TABLE clipboard_entries; -- app:a'--";
+        let compiled = compile(&parse(input).unwrap());
+        assert!(!compiled.where_sql.contains("DROP"));
+        assert!(!compiled.where_sql.contains('\''));
+        assert_eq!(compiled.params, vec![Value::Text("a'--".to_string())]);
+        assert!(compiled.match_expr.unwrap().contains("\"DROP\"*"));
+    }
+
+    #[test]
+    fn test_fts_operator_injection_is_quoted() {
+        // FTS5 syntax in user input must stay inside quoted strings
+        let compiled = compile(&parse("NEAR(a b) text_content:secret ^start a\"b").unwrap());
+        let expr = compiled.match_expr.unwrap();
+        assert_eq!(expr, "\"NEAR\"* AND \"a\"* AND \"b\"* AND \"text_content:secret\"* AND \"^start\"* AND \"ab\"*");
+
+        assert_eq!(fts_term("say \"hi\"", false), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_substring_ranges() {
+        let terms = vec!["東京".to_string(), "京都".to_string(), "ab".to_string()];
+        // Overlapping matches merge; offsets are UTF-16, so the emoji counts twice
+        assert_eq!(
+            substring_ranges("😀東京都とAB", &terms),
+            vec![MatchRange { start: 2, end: 5 }, MatchRange { start: 6, end: 8 }]
+        );
+        assert!(substring_ranges("大阪", &terms).is_empty());
+
+        let more = substring_bm25("住所 住所", &["住所".to_string()]);
+        let once = substring_bm25("住所", &["住所".to_string()]);
+        let longer = substring_bm25(&format!("住所{}", "あ".repeat(400)), &["住所".to_string()]);
+        assert!(more < once && once < longer && longer < 0.0);
+    }
+
+    #[test]
+    fn test_compile_cjk_terms_use_substring_match() {
+        let compiled = compile(&parse("kubectl 東京都庁 東京").unwrap());
+        assert_eq!(compiled.match_expr.as_deref(), Some("\"kubectl\"*"));
+        assert_eq!(
+            compiled.where_sql,
+            "e.id IN (SELECT rowid FROM clipboard_fts_trigram WHERE clipboard_fts_trigram MATCH ?) \
+             AND COALESCE(e.text_content, '') LIKE ? ESCAPE '\\'"
+        );
+        assert_eq!(compiled.params, vec![
+            Value::Text("\"東京都庁\"".to_string()),
+            Value::Text("%東京%".to_string()),
+        ]);
+
+        assert!(is_cjk("한국어"));
+        assert!(is_cjk("ｶﾀｶﾅ"));
+        assert!(!is_cjk("café"));
+    }
+
+    #[test]
+    fn test_split_text_terms() {
+        let (terms, rest) = split_text_terms(&parse("kubectl app:Terminal \"get podz\" -draft").unwrap());
+        assert_eq!(terms, vec!["kubectl", "get podz"]);
+        assert_eq!(rest, Some(Expr::And(vec![
+            Expr::Filter(Filter::App("Terminal".to_string())),
+            Expr::Not(Box::new(text("draft"))),
+        ])));
+
+        let (terms, rest) = split_text_terms(&parse("podz").unwrap());
+        assert_eq!(terms, vec!["podz"]);
+        assert_eq!(rest, None);
+    }
+
+    #[test]
+    fn test_relevance_score() {
+        let now = 100 * 86_400_000;
+        let base = relevance_score(Some(-2.0), now, 1, now);
+        assert_eq!(base, 2.0);
+        assert_eq!(relevance_score(Some(-2.0), now - 30 * 86_400_000, 1, now), 1.0);
+        assert!(relevance_score(Some(-2.0), now, 5, now) > base);
+        assert!(relevance_score(Some(-4.0), now, 1, now) > base);
+        assert_eq!(relevance_score(None, now, 1, now), 1.0);
+        // Clock skew never boosts above a fresh entry
+        assert_eq!(relevance_score(Some(-2.0), now + 1000, 1, now), base);
+    }
+}
@@ -0,0 +1,268 @@
+//! Retention policies for `Storage::apply_retention`.
+//!
+//! ```json
+//! {
+//!   "max_age_days": 90,
+//!   "max_entries": 5000,
+//!   "max_total_bytes": 1073741824,
+//!   "type_max_age_days": { "Image": 2 },
+//!   "app_max_age_days": { "Terminal": 365 }
+//! }
+//! ```
+//!
+//! The age limit for an entry is its source app's override if any, else its
+//! content type's, else `max_age_days`. After expired entries are removed,
+//! the oldest remaining ones go until both `max_entries` and
+//! `max_total_bytes` hold. Pinned entries are never removed but do count
+//! toward both totals. An image blob shared by several entries counts once,
+//! and is only freed when the last of them goes.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::ContentType;
+
+const MILLIS_PER_DAY: i64 = 86_400_000;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub max_age_days: Option<u32>,
+    pub max_entries: Option<u64>,
+    /// Text and thumbnail bytes of each entry plus the bytes of each image
+    /// blob, counted once however many entries share it.
+    pub max_total_bytes: Option<u64>,
+    /// Age limits by `ContentType` name, overriding `max_age_days`.
+    pub type_max_age_days: HashMap<ContentType, u32>,
+    /// Age limits by source app (case-insensitive), overriding both of the above.
+    pub app_max_age_days: HashMap<String, u32>,
+}
+
+impl RetentionPolicy {
+    fn max_age_days(&self, candidate: &Candidate, apps: &HashMap<String, u32>) -> Option<u32> {
+        candidate.source_app.as_ref()
+            .and_then(|app| apps.get(&app.to_lowercase()))
+            .or_else(|| self.type_max_age_days.get(&candidate.content_type))
+            .or(self.max_age_days.as_ref())
+            .copied()
+    }
+}
+
+/// Why entries were removed, in the order the rules are applied.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RemovalReasons {
+    pub expired: u64,
+    pub over_count: u64,
+    pub over_size: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RetentionReport {
+    /// Removed entry ids, oldest first.
+    pub removed_ids: Vec<i64>,
+    pub removed_entries: u64,
+    pub removed_bytes: u64,
+    pub removed_by_reason: RemovalReasons,
+    /// Removed entry counts keyed by `ContentType` name.
+    pub removed_by_type: BTreeMap<String, u64>,
+    pub remaining_entries: u64,
+    pub remaining_bytes: u64,
+}
+
+/// An entry as seen by the planner.
+#[derive(Debug, Clone)]
+pub(crate) struct Candidate {
+    pub id: i64,
+    pub content_type: ContentType,
+    pub source_app: Option<String>,
+    pub created_at: i64,
+    pub pinned: bool,
+    /// Text and thumbnail bytes, which belong to this entry alone.
+    pub size: u64,
+    pub blob_hash: Option<String>,
+    pub blob_size: u64,
+}
+
+/// Bytes held by the entries not yet removed.
+struct Usage<'a> {
+    bytes: u64,
+    blob_refs: HashMap<&'a str, usize>,
+}
+
+impl<'a> Usage<'a> {
+    fn new(candidates: &'a [Candidate]) -> Self {
+        let mut usage = Usage { bytes: 0, blob_refs: HashMap::new() };
+        for candidate in candidates {
+            usage.bytes += candidate.size;
+            if let Some(hash) = candidate.blob_hash.as_deref() {
+                let refs = usage.blob_refs.entry(hash).or_default();
+                if *refs == 0 {
+                    usage.bytes += candidate.blob_size;
+                }
+                *refs += 1;
+            }
+        }
+        usage
+    }
+
+    fn remove(&mut self, candidate: &'a Candidate) {
+        self.bytes -= candidate.size;
+        if let Some(refs) = candidate.blob_hash.as_deref().and_then(|hash| self.blob_refs.get_mut(hash)) {
+            *refs -= 1;
+            if *refs == 0 {
+                self.bytes -= candidate.blob_size;
+            }
+        }
+    }
+}
+
+/// Decides what `policy` removes from `candidates`, which must be ordered
+/// oldest first.
+pub(crate) fn plan(policy: &RetentionPolicy, candidates: &[Candidate], now: i64) -> RetentionReport {
+    let apps: HashMap<String, u32> = policy.app_max_age_days.iter()
+        .map(|(app, days)| (app.to_lowercase(), *days))
+        .collect();
+    let mut report = RetentionReport::default();
+    let mut removed = vec![false; candidates.len()];
+    let mut usage = Usage::new(candidates);
+    let total_bytes = usage.bytes;
+
+    for (i, candidate) in candidates.iter().enumerate() {
+        let expired = policy.max_age_days(candidate, &apps)
+            .is_some_and(|days| candidate.created_at < now - days as i64 * MILLIS_PER_DAY);
+        if expired && !candidate.pinned {
+            removed[i] = true;
+            usage.remove(candidate);
+            report.removed_by_reason.expired += 1;
+        }
+    }
+
+    let mut count = removed.iter().filter(|r| !**r).count() as u64;
+    let max_entries = policy.max_entries.unwrap_or(u64::MAX);
+    let max_bytes = policy.max_total_bytes.unwrap_or(u64::MAX);
+    for (i, candidate) in candidates.iter().enumerate() {
+        if count <= max_entries && usage.bytes <= max_bytes {
+            break;
+        }
+        if removed[i] || candidate.pinned {
+            continue;
+        }
+        if count > max_entries {
+            report.removed_by_reason.over_count += 1;
+        } else {
+            report.removed_by_reason.over_size += 1;
+        }
+        removed[i] = true;
+        count -= 1;
+        usage.remove(candidate);
+    }
+
+    for (candidate, _) in candidates.iter().zip(&removed).filter(|(_, r)| **r) {
+        report.removed_ids.push(candidate.id);
+        *report.removed_by_type.entry(candidate.content_type.as_str().to_string()).or_default() += 1;
+    }
+    report.removed_entries = report.removed_ids.len() as u64;
+    report.remaining_entries = count;
+    report.removed_bytes = total_bytes - usage.bytes;
+    report.remaining_bytes = usage.bytes;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1000 * MILLIS_PER_DAY;
+
+    fn candidate(id: i64, content_type: ContentType, app: &str, age_days: i64, size: u64) -> Candidate {
+        Candidate {
+            id,
+            content_type,
+            source_app: Some(app.to_string()),
+            created_at: NOW - age_days * MILLIS_PER_DAY - 1,
+            pinned: false,
+            size,
+            blob_hash: None,
+            blob_size: 0,
+        }
+    }
+
+    #[test]
+    fn test_age_limits_by_type_and_app() {
+        let policy: RetentionPolicy = serde_json::from_str(
+            r#"{"max_age_days": 90, "type_max_age_days": {"Image": 2}, "app_max_age_days": {"terminal": 365}}"#,
+        ).unwrap();
+        let candidates = [
+            candidate(1, ContentType::PlainText, "Notes", 100, 10),
+            candidate(2, ContentType::PlainText, "Terminal", 100, 10),
+            candidate(3, ContentType::Image, "Preview", 3, 1000),
+            candidate(4, ContentType::Image, "Terminal", 3, 1000),
+            candidate(5, ContentType::PlainText, "Notes", 1, 10),
+        ];
+
+        let report = plan(&policy, &candidates, NOW);
+        assert_eq!(report.removed_ids, vec![1, 3]);
+        assert_eq!(report.removed_by_reason, RemovalReasons { expired: 2, ..Default::default() });
+        assert_eq!(report.removed_bytes, 1010);
+        assert_eq!(report.removed_by_type.get("Image"), Some(&1));
+        assert_eq!(report.remaining_entries, 3);
+        assert_eq!(report.remaining_bytes, 1020);
+    }
+
+    #[test]
+    fn test_count_and_size_limits_remove_oldest_unpinned() {
+        let mut candidates: Vec<Candidate> = (1..=5)
+            .map(|id| candidate(id, ContentType::PlainText, "App", 10 - id, 100))
+            .collect();
+        candidates[0].pinned = true;
+
+        let policy = RetentionPolicy { max_entries: Some(3), ..Default::default() };
+        let report = plan(&policy, &candidates, NOW);
+        assert_eq!(report.removed_ids, vec![2, 3]);
+        assert_eq!(report.removed_by_reason.over_count, 2);
+
+        let policy = RetentionPolicy { max_entries: Some(4), max_total_bytes: Some(250), ..Default::default() };
+        let report = plan(&policy, &candidates, NOW);
+        assert_eq!(report.removed_ids, vec![2, 3, 4]);
+        assert_eq!(report.removed_by_reason, RemovalReasons { expired: 0, over_count: 1, over_size: 2 });
+        assert_eq!(report.remaining_bytes, 200);
+
+        // Pinned entries alone exceeding the limit are kept
+        let policy = RetentionPolicy { max_entries: Some(0), ..Default::default() };
+        let report = plan(&policy, &candidates, NOW);
+        assert_eq!(report.removed_entries, 4);
+        assert_eq!(report.remaining_entries, 1);
+    }
+
+    #[test]
+    fn test_shared_blob_counts_once() {
+        let mut candidates: Vec<Candidate> = (1..=3)
+            .map(|id| candidate(id, ContentType::Image, "App", 10 - id, 10))
+            .collect();
+        for c in &mut candidates[..2] {
+            c.blob_hash = Some("shared".to_string());
+            c.blob_size = 1000;
+        }
+        candidates[2].blob_hash = Some("own".to_string());
+        candidates[2].blob_size = 500;
+
+        let report = plan(&RetentionPolicy::default(), &candidates, NOW);
+        assert_eq!(report.remaining_bytes, 30 + 1000 + 500);
+
+        // Removing one sharer frees only its own bytes, so both go
+        let policy = RetentionPolicy { max_total_bytes: Some(1000), ..Default::default() };
+        let report = plan(&policy, &candidates, NOW);
+        assert_eq!(report.removed_ids, vec![1, 2]);
+        assert_eq!(report.removed_bytes, 20 + 1000);
+        assert_eq!(report.remaining_bytes, 510);
+    }
+
+    #[test]
+    fn test_empty_policy_removes_nothing() {
+        let candidates = [candidate(1, ContentType::Image, "App", 10_000, 1 << 30)];
+        let report = plan(&RetentionPolicy::default(), &candidates, NOW);
+        assert!(report.removed_ids.is_empty());
+        assert_eq!(report.remaining_entries, 1);
+    }
+}
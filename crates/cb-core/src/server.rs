@@ -0,0 +1,527 @@
+//! JSON-RPC 2.0 over a Unix domain socket, so editor plugins and terminal
+//! tools can use the history while the app holds the database. Built with
+//! the `server` feature.
+//!
+//! Each message is one JSON object on one line. A connection must first call
+//! `auth` with the token the server wrote to its token file, which only the
+//! owner can read (mode 0600); a wrong token closes the connection.
+//!
+//! | Method | Params | Result |
+//! |--------|--------|--------|
+//! | `auth` | `token` | `true` |
+//! | `list` | `limit`, `cursor`, `filter` (`EntryFilter`), all optional | `Page` of `ClipboardEntry`, newest first |
+//! | `search` | `query`, `limit` | `SearchHit` array |
+//! | `get` | `id`, `image` | `{entry, image_base64}`; the image only when `image` is true |
+//! | `add` | `text` and `content_type`, or `image_base64`; `source_app`, `bundle_id` | `SaveOutcome` |
+//! | `delete` | `id` | whether the entry existed |
+//! | `subscribe` | — | `true`, then `changed` notifications |
+//!
+//! `changed` notifications carry `{newest}`, the newest entry or `null`.
+//! The server polls `Storage::change_marker`, so writes from the app, from
+//! clients and from other processes such as `cb` are all reported.
+//!
+//! Storage errors use code `-32000` with the `Error` code and name in `data`.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::fs::{FileTypeExt, OpenOptionsExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use crate::error::{Error, Result};
+use crate::models::{ContentType, Cursor, EntryFilter, SaveOutcome};
+use crate::storage::Storage;
+use crate::thumbnail;
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Longest request line; enough for a base64 screenshot.
+const MAX_REQUEST_BYTES: u64 = 64 * 1024 * 1024;
+/// A subscriber that stops reading is dropped rather than stalling others.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const STORAGE_ERROR: i64 = -32000;
+const UNAUTHORIZED: i64 = -32001;
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub socket_path: PathBuf,
+    /// Rewritten with a fresh token on every start.
+    pub token_path: PathBuf,
+    /// How often to check the database for `changed` notifications.
+    pub poll_interval: Duration,
+}
+
+impl ServerConfig {
+    pub fn new(socket_path: impl Into<PathBuf>, token_path: impl Into<PathBuf>) -> Self {
+        ServerConfig {
+            socket_path: socket_path.into(),
+            token_path: token_path.into(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError { code, message: message.into(), data: None }
+    }
+}
+
+impl From<Error> for RpcError {
+    fn from(e: Error) -> Self {
+        RpcError {
+            code: STORAGE_ERROR,
+            message: e.to_string(),
+            data: Some(json!({ "code": e.code(), "name": e.name() })),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Absent for notifications, which get no response.
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuthParams {
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct ListParams {
+    limit: i32,
+    /// A previous page's `next_cursor`; absent for the newest page.
+    cursor: Option<String>,
+    filter: EntryFilter,
+}
+
+impl Default for ListParams {
+    fn default() -> Self {
+        ListParams { limit: 50, cursor: None, filter: EntryFilter::default() }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    query: String,
+    #[serde(default = "default_limit")]
+    limit: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct GetParams {
+    id: i64,
+    #[serde(default)]
+    image: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddParams {
+    text: Option<String>,
+    #[serde(default)]
+    content_type: Option<ContentType>,
+    image_base64: Option<String>,
+    #[serde(default)]
+    source_app: String,
+    #[serde(default)]
+    bundle_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeleteParams {
+    id: i64,
+}
+
+fn default_limit() -> i32 {
+    50
+}
+
+/// One connection. Responses and notifications share the stream, so writes
+/// take the lock.
+struct Client {
+    stream: Mutex<UnixStream>,
+    subscribed: AtomicBool,
+}
+
+impl Client {
+    fn send(&self, message: &Value) -> std::io::Result<()> {
+        let mut stream = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        stream.write_all(&line)?;
+        stream.flush()
+    }
+
+    fn close(&self) {
+        let stream = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+}
+
+struct Shared {
+    storage: &'static Mutex<Option<Storage>>,
+    token: String,
+    clients: Mutex<Vec<Arc<Client>>>,
+    stopping: AtomicBool,
+}
+
+impl Shared {
+    fn with_storage<T>(&self, f: impl FnOnce(&Storage) -> Result<T>) -> Result<T> {
+        let guard = self.storage.lock().map_err(|_| Error::LockPoisoned)?;
+        match guard.as_ref() {
+            Some(storage) => f(storage),
+            None => Err(Error::NotInitialized),
+        }
+    }
+
+    fn clients(&self) -> std::sync::MutexGuard<'_, Vec<Arc<Client>>> {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn remove(&self, client: &Arc<Client>) {
+        self.clients().retain(|c| !Arc::ptr_eq(c, client));
+    }
+}
+
+/// A running server. Stopping it, or dropping it, closes every connection
+/// and removes the socket and token files.
+pub struct Server {
+    shared: Arc<Shared>,
+    config: ServerConfig,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Server {
+    /// Serves `storage`, normally the process-wide storage the FFI uses,
+    /// until stopped. Fails if another server is listening on the socket.
+    pub fn start(storage: &'static Mutex<Option<Storage>>, config: ServerConfig) -> Result<Self> {
+        let listener = bind(&config.socket_path)?;
+        let token = new_token()?;
+        if let Err(e) = write_token(&config.token_path, &token) {
+            let _ = std::fs::remove_file(&config.socket_path);
+            return Err(e);
+        }
+
+        let shared = Arc::new(Shared {
+            storage,
+            token,
+            clients: Mutex::new(Vec::new()),
+            stopping: AtomicBool::new(false),
+        });
+        let accept = {
+            let shared = Arc::clone(&shared);
+            std::thread::spawn(move || accept_loop(shared, listener))
+        };
+        let watch = {
+            let shared = Arc::clone(&shared);
+            let interval = config.poll_interval;
+            std::thread::spawn(move || watch_loop(shared, interval))
+        };
+        Ok(Server { shared, config, threads: vec![accept, watch] })
+    }
+
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    pub fn stop(&mut self) {
+        if self.shared.stopping.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        // Wake the accept loop, which then sees `stopping`
+        let _ = UnixStream::connect(&self.config.socket_path);
+        for client in self.shared.clients().drain(..) {
+            client.close();
+        }
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+        let _ = std::fs::remove_file(&self.config.socket_path);
+        let _ = std::fs::remove_file(&self.config.token_path);
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Binds the socket, replacing a stale one left by a crashed server, and
+/// limits it to the owner.
+fn bind(path: &Path) -> Result<UnixListener> {
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(Error::Io(format!("{} exists and is not a socket", path.display())));
+        }
+        if UnixStream::connect(path).is_ok() {
+            return Err(Error::Io(format!("another server is listening on {}", path.display())));
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+fn new_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| Error::Io(e.to_string()))?;
+    Ok(crate::cipher::to_hex(&bytes))
+}
+
+/// Writes the token to a new file readable only by the owner. The old file
+/// is removed first so an existing symlink is never followed.
+fn write_token(path: &Path, token: &str) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut file = std::fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)?;
+    // The umask can only narrow the mode, but be exact
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(token.as_bytes())?;
+    Ok(())
+}
+
+/// Compares in time independent of where the first difference is.
+fn token_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected.bytes().zip(given.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+fn accept_loop(shared: Arc<Shared>, listener: UnixListener) {
+    for stream in listener.incoming() {
+        if shared.stopping.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(stream) = stream else { continue };
+        let Ok(reader) = stream.try_clone() else { continue };
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+        let client = Arc::new(Client { stream: Mutex::new(stream), subscribed: AtomicBool::new(false) });
+        shared.clients().push(Arc::clone(&client));
+        let shared = Arc::clone(&shared);
+        std::thread::spawn(move || {
+            serve(&shared, &client, reader);
+            shared.remove(&client);
+        });
+    }
+}
+
+fn serve(shared: &Shared, client: &Client, stream: UnixStream) {
+    let mut reader = BufReader::new(stream);
+    let mut authenticated = false;
+    loop {
+        let mut line = String::new();
+        match (&mut reader).take(MAX_REQUEST_BYTES).read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {}
+        }
+        if !line.ends_with('\n') && line.len() as u64 == MAX_REQUEST_BYTES {
+            let error = RpcError::new(INVALID_REQUEST, "request too large");
+            let _ = client.send(&response(Value::Null, Err(error)));
+            return;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (reply, close) = handle(shared, client, &mut authenticated, &line);
+        if let Some(reply) = reply
+            && client.send(&reply).is_err()
+        {
+            return;
+        }
+        if close {
+            client.close();
+            return;
+        }
+    }
+}
+
+fn response(id: Value, result: std::result::Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => {
+            let mut error = json!({ "code": e.code, "message": e.message });
+            if let Some(data) = e.data {
+                error["data"] = data;
+            }
+            json!({ "jsonrpc": "2.0", "id": id, "error": error })
+        }
+    }
+}
+
+/// The reply to one request line, if any, and whether to close afterwards.
+fn handle(shared: &Shared, client: &Client, authenticated: &mut bool, line: &str) -> (Option<Value>, bool) {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => return (Some(response(Value::Null, Err(RpcError::new(PARSE_ERROR, e.to_string())))), false),
+    };
+    let request: Request = match serde_json::from_value(value) {
+        Ok(request) => request,
+        Err(e) => {
+            return (Some(response(Value::Null, Err(RpcError::new(INVALID_REQUEST, e.to_string())))), false);
+        }
+    };
+
+    let (result, close) = if request.method == "auth" {
+        match params::<AuthParams>(request.params) {
+            Ok(auth) if token_matches(&shared.token, &auth.token) => {
+                *authenticated = true;
+                (Ok(json!(true)), false)
+            }
+            Ok(_) => (Err(RpcError::new(UNAUTHORIZED, "wrong token")), true),
+            Err(e) => (Err(e), false),
+        }
+    } else if !*authenticated {
+        (Err(RpcError::new(UNAUTHORIZED, "call auth first")), false)
+    } else {
+        (dispatch(shared, client, &request.method, request.params), false)
+    };
+    (request.id.map(|id| response(id, result)), close)
+}
+
+fn params<T: for<'de> Deserialize<'de>>(params: Value) -> std::result::Result<T, RpcError> {
+    // Omitted params mean all defaults
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn dispatch(shared: &Shared, client: &Client, method: &str, raw: Value) -> std::result::Result<Value, RpcError> {
+    let value = match method {
+        "list" => {
+            let p: ListParams = params(raw)?;
+            let cursor = p.cursor.as_deref().map(Cursor::decode).transpose()?;
+            json!(shared.with_storage(|s| s.list_entries(&p.filter, cursor.as_ref(), p.limit))?)
+        }
+        "search" => {
+            let p: SearchParams = params(raw)?;
+            json!(shared.with_storage(|s| s.search_entries_with_snippets(&p.query, p.limit))?)
+        }
+        "get" => {
+            let p: GetParams = params(raw)?;
+            shared.with_storage(|s| {
+                let entry = s.get_entry(p.id)?.ok_or(Error::NotFound)?;
+                let image = if p.image { s.get_entry_image(p.id)? } else { None };
+                Ok(json!({ "entry": entry, "image_base64": image.map(|data| BASE64.encode(data)) }))
+            })?
+        }
+        "add" => json!(add(shared, params(raw)?)?),
+        "delete" => {
+            let p: DeleteParams = params(raw)?;
+            json!(shared.with_storage(|s| s.delete_entry(p.id))?)
+        }
+        "subscribe" => {
+            client.subscribed.store(true, Ordering::SeqCst);
+            json!(true)
+        }
+        _ => return Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method: {method}"))),
+    };
+    Ok(value)
+}
+
+/// Saves like a copy in the app: ignore rules first, then the sensitive
+/// rules and merging inside `Storage`.
+fn add(shared: &Shared, p: AddParams) -> std::result::Result<SaveOutcome, RpcError> {
+    let image = match (&p.text, &p.image_base64) {
+        (Some(_), None) => None,
+        (None, Some(encoded)) => Some(
+            BASE64.decode(encoded).map_err(|e| RpcError::new(INVALID_PARAMS, format!("image_base64: {e}")))?,
+        ),
+        _ => return Err(RpcError::new(INVALID_PARAMS, "pass exactly one of text and image_base64")),
+    };
+    // Decode outside the storage lock so other clients are not held up
+    let thumb = image.as_deref().and_then(|data| thumbnail::generate(data, thumbnail::STORED_MAX_PX).ok());
+    let outcome = shared.with_storage(|s| {
+        if let Some(app) = s.find_ignored_app(&p.source_app, &p.bundle_id)? {
+            return Ok(SaveOutcome::Ignored { pattern: app.pattern });
+        }
+        match (image, p.text) {
+            (Some(image), _) => s.insert_image_entry_with_thumbnail(&image, &p.source_app, &p.bundle_id, thumb),
+            (None, text) => {
+                let content_type = p.content_type.unwrap_or(ContentType::PlainText);
+                s.insert_text_entry_from(&content_type, &text.unwrap_or_default(), &p.source_app, &p.bundle_id, None)
+            }
+        }
+    })?;
+    Ok(outcome)
+}
+
+/// Sends `changed` to subscribers whenever the change marker moves.
+fn watch_loop(shared: Arc<Shared>, interval: Duration) {
+    let mut last = shared.with_storage(|s| s.change_marker()).ok();
+    while !shared.stopping.load(Ordering::SeqCst) {
+        std::thread::sleep(interval);
+        let Ok(marker) = shared.with_storage(|s| s.change_marker()) else { continue };
+        if last == Some(marker) {
+            continue;
+        }
+        last = Some(marker);
+        let subscribers: Vec<_> = shared.clients().iter()
+            .filter(|c| c.subscribed.load(Ordering::SeqCst))
+            .cloned()
+            .collect();
+        if subscribers.is_empty() {
+            continue;
+        }
+        let Ok(newest) = shared.with_storage(|s| s.get_recent_entries(1)) else { continue };
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "changed",
+            "params": { "newest": newest.into_iter().next() },
+        });
+        for client in subscribers {
+            if client.send(&notification).is_err() {
+                client.close();
+                shared.remove(&client);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_matches() {
+        assert!(token_matches("abc", "abc"));
+        assert!(!token_matches("abc", "abd"));
+        assert!(!token_matches("abc", "ab"));
+        assert!(!token_matches("abc", ""));
+    }
+
+    #[test]
+    fn test_params_default_when_omitted() {
+        let list: ListParams = params(Value::Null).unwrap();
+        assert_eq!((list.limit, list.cursor), (50, None));
+        let error = params::<DeleteParams>(json!({ "id": "x" })).unwrap_err();
+        assert_eq!(error.code, INVALID_PARAMS);
+    }
+}
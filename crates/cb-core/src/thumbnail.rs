@@ -0,0 +1,121 @@
+//! Image decoding and thumbnail generation for image entries.
+//!
+//! PNG, JPEG and TIFF are decoded with the pure-Rust `image` crate.
+//! Thumbnails are always PNG so transparency survives; images already
+//! within the requested size are re-encoded at their original size.
+
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat};
+
+use crate::error::{Error, Result};
+
+/// Longest side of the thumbnail stored alongside each image entry.
+pub const STORED_MAX_PX: u32 = 256;
+
+/// Dimensions and format of a decoded image, plus its PNG thumbnail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    /// Width of the original image.
+    pub width: u32,
+    /// Height of the original image.
+    pub height: u32,
+    /// `"png"`, `"jpeg"` or `"tiff"`.
+    pub format: &'static str,
+    pub png: Vec<u8>,
+}
+
+fn format_name(format: ImageFormat) -> Option<&'static str> {
+    match format {
+        ImageFormat::Png => Some("png"),
+        ImageFormat::Jpeg => Some("jpeg"),
+        ImageFormat::Tiff => Some("tiff"),
+        _ => None,
+    }
+}
+
+fn decode(data: &[u8]) -> Result<(DynamicImage, &'static str)> {
+    let format = image::guess_format(data)
+        .ok()
+        .and_then(|f| format_name(f).map(|name| (f, name)));
+    let Some((format, name)) = format else {
+        return Err(Error::InvalidInput("unsupported image format".to_string()));
+    };
+    let image = image::load_from_memory_with_format(data, format)
+        .map_err(|e| Error::Corrupt(format!("image decode failed: {e}")))?;
+    Ok((image, name))
+}
+
+fn encode_png(image: &DynamicImage, max_px: u32) -> Result<Vec<u8>> {
+    let scaled;
+    let image = if image.width() > max_px || image.height() > max_px {
+        scaled = image.thumbnail(max_px, max_px);
+        &scaled
+    } else {
+        image
+    };
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| Error::Serialization(format!("thumbnail encode failed: {e}")))?;
+    Ok(png)
+}
+
+/// Decodes `data` and renders a thumbnail whose longest side is at most
+/// `max_px`, keeping the aspect ratio.
+pub fn generate(data: &[u8], max_px: u32) -> Result<Thumbnail> {
+    if max_px == 0 {
+        return Err(Error::InvalidInput("max_px must be positive".to_string()));
+    }
+    let (image, format) = decode(data)?;
+    Ok(Thumbnail {
+        width: image.width(),
+        height: image.height(),
+        format,
+        png: encode_png(&image, max_px)?,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    /// Encodes a solid test image in `format`.
+    pub(crate) fn sample_image(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([200, 40, 40, 255])));
+        let image = if format == ImageFormat::Jpeg { DynamicImage::ImageRgb8(image.to_rgb8()) } else { image };
+        let mut data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut data), format).unwrap();
+        data
+    }
+
+    fn dimensions(png: &[u8]) -> (u32, u32) {
+        let image = image::load_from_memory_with_format(png, ImageFormat::Png).unwrap();
+        (image.width(), image.height())
+    }
+
+    #[test]
+    fn test_generate_for_each_format() {
+        for (format, name) in [(ImageFormat::Png, "png"), (ImageFormat::Jpeg, "jpeg"), (ImageFormat::Tiff, "tiff")] {
+            let thumb = generate(&sample_image(800, 400, format), 256).unwrap();
+            assert_eq!((thumb.width, thumb.height, thumb.format), (800, 400, name));
+            assert_eq!(dimensions(&thumb.png), (256, 128));
+        }
+    }
+
+    #[test]
+    fn test_small_images_are_not_upscaled() {
+        let thumb = generate(&sample_image(40, 30, ImageFormat::Png), 256).unwrap();
+        assert_eq!(dimensions(&thumb.png), (40, 30));
+    }
+
+    #[test]
+    fn test_rejects_unsupported_data() {
+        assert!(matches!(generate(&[1, 2, 3], 256), Err(Error::InvalidInput(_))));
+        assert!(matches!(generate(b"GIF89a", 256), Err(Error::InvalidInput(_))));
+        // Valid PNG signature followed by garbage
+        let mut broken = sample_image(10, 10, ImageFormat::Png);
+        broken.truncate(20);
+        assert!(matches!(generate(&broken, 256), Err(Error::Corrupt(_))));
+    }
+}
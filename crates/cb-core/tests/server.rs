@@ -0,0 +1,198 @@
+//! Talks to a running `server::Server` the way an editor plugin would:
+//! read the token file, connect, authenticate, send JSON-RPC lines.
+
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use cb_core::models::ContentType;
+use cb_core::server::{Server, ServerConfig};
+use cb_core::storage::Storage;
+use serde_json::{Value, json};
+
+/// A stand-in client speaking newline-delimited JSON-RPC.
+struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    next_id: i64,
+}
+
+impl Client {
+    fn connect(config: &ServerConfig) -> Self {
+        let writer = UnixStream::connect(&config.socket_path).unwrap();
+        writer.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let reader = BufReader::new(writer.try_clone().unwrap());
+        Client { reader, writer, next_id: 1 }
+    }
+
+    fn authenticated(config: &ServerConfig) -> Self {
+        let mut client = Client::connect(config);
+        let token = std::fs::read_to_string(&config.token_path).unwrap();
+        assert_eq!(client.call("auth", json!({ "token": token }))["result"], true);
+        client
+    }
+
+    fn send_line(&mut self, line: &str) {
+        self.writer.write_all(line.as_bytes()).unwrap();
+        self.writer.write_all(b"\n").unwrap();
+    }
+
+    /// The next message, or `None` once the server closed the connection.
+    fn receive(&mut self) -> Option<Value> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line).unwrap() {
+            0 => None,
+            _ => Some(serde_json::from_str(&line).unwrap()),
+        }
+    }
+
+    /// Sends a request and returns its response, skipping notifications.
+    fn call(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        self.send_line(&request.to_string());
+        loop {
+            let message = self.receive().expect("connection closed");
+            if message["id"] == id {
+                return message;
+            }
+        }
+    }
+
+    fn notification(&mut self) -> Value {
+        let message = self.receive().expect("connection closed");
+        assert_eq!(message["method"], "changed");
+        message
+    }
+}
+
+/// A fresh database behind its own storage mutex, and a server config in
+/// a scratch directory.
+fn setup(name: &str) -> (&'static Mutex<Option<Storage>>, ServerConfig) {
+    let dir = std::env::temp_dir().join(format!("cb_server_{name}"));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let storage = Storage::new(dir.join("clipboard.db").to_str().unwrap(), None).unwrap();
+    let storage = Box::leak(Box::new(Mutex::new(Some(storage))));
+    let mut config = ServerConfig::new(dir.join("cb.sock"), dir.join("token"));
+    config.poll_interval = Duration::from_millis(20);
+    (storage, config)
+}
+
+fn error_code(response: &Value) -> i64 {
+    response["error"]["code"].as_i64().unwrap()
+}
+
+#[test]
+fn test_auth() {
+    let (storage, config) = setup("auth");
+    let _server = Server::start(storage, config.clone()).unwrap();
+    let mode = std::fs::metadata(&config.token_path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+    let mode = std::fs::metadata(&config.socket_path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    let mut client = Client::connect(&config);
+    assert_eq!(error_code(&client.call("list", json!({}))), -32001);
+    let wrong = client.call("auth", json!({ "token": "0".repeat(64) }));
+    assert_eq!(error_code(&wrong), -32001);
+    assert!(client.receive().is_none());
+
+    let mut client = Client::authenticated(&config);
+    assert_eq!(client.call("list", Value::Null)["result"]["items"], json!([]));
+}
+
+#[test]
+fn test_methods() {
+    let (storage, config) = setup("methods");
+    let _server = Server::start(storage, config.clone()).unwrap();
+    let mut client = Client::authenticated(&config);
+
+    let added = client.call("add", json!({ "text": "cargo test --workspace", "source_app": "Terminal" }));
+    let text_id = added["result"]["id"].as_i64().unwrap();
+    let path = client.call("add", json!({ "text": "/tmp/a.txt", "content_type": "FilePath" }));
+    assert_eq!(path["result"]["status"], "stored");
+    let image = client.call("add", json!({ "image_base64": "AAEC", "source_app": "Preview" }));
+    let image_id = image["result"]["id"].as_i64().unwrap();
+
+    let listed = client.call("list", json!({ "limit": 10, "filter": { "content_types": ["FilePath"] } }));
+    assert_eq!(listed["result"]["items"].as_array().unwrap().len(), 1);
+    assert_eq!(listed["result"]["items"][0]["text_content"], "/tmp/a.txt");
+    assert_eq!(client.call("list", json!({}))["result"]["items"].as_array().unwrap().len(), 3);
+
+    let first = client.call("list", json!({ "limit": 2 }));
+    assert_eq!(first["result"]["items"].as_array().unwrap().len(), 2);
+    let rest = client.call("list", json!({ "limit": 2, "cursor": first["result"]["next_cursor"] }));
+    assert_eq!(rest["result"]["items"].as_array().unwrap().len(), 1);
+    assert_eq!(rest["result"]["next_cursor"], Value::Null);
+
+    let hits = client.call("search", json!({ "query": "workspace" }));
+    assert_eq!(hits["result"][0]["id"], text_id);
+
+    let got = client.call("get", json!({ "id": image_id, "image": true }));
+    assert_eq!(got["result"]["entry"]["content_type"], "Image");
+    assert_eq!(got["result"]["image_base64"], "AAEC");
+    assert_eq!(client.call("get", json!({ "id": text_id }))["result"]["image_base64"], Value::Null);
+
+    assert_eq!(client.call("delete", json!({ "id": text_id }))["result"], true);
+    assert_eq!(client.call("delete", json!({ "id": text_id }))["result"], false);
+    let missing = client.call("get", json!({ "id": text_id }));
+    assert_eq!(error_code(&missing), -32000);
+    assert_eq!(missing["error"]["data"]["name"], "not_found");
+
+    assert_eq!(error_code(&client.call("paste", json!({}))), -32601);
+    assert_eq!(error_code(&client.call("get", json!({ "id": "one" }))), -32602);
+    assert_eq!(error_code(&client.call("add", json!({}))), -32602);
+    client.send_line("{not json");
+    assert_eq!(error_code(&client.receive().unwrap()), -32700);
+    // Notifications get no response; the next reply is for the next call
+    client.send_line(r#"{"jsonrpc":"2.0","method":"delete","params":{"id":0}}"#);
+    assert_eq!(client.call("subscribe", Value::Null)["result"], true);
+}
+
+#[test]
+fn test_subscribe() {
+    let (storage, config) = setup("subscribe");
+    let _server = Server::start(storage, config.clone()).unwrap();
+    let mut watcher = Client::authenticated(&config);
+    assert_eq!(watcher.call("subscribe", Value::Null)["result"], true);
+
+    // A write from another client
+    let mut writer = Client::authenticated(&config);
+    writer.call("add", json!({ "text": "from a client" }));
+    assert_eq!(watcher.notification()["params"]["newest"]["text_content"], "from a client");
+
+    // A write by the app through the shared storage
+    let guard = storage.lock().unwrap();
+    guard.as_ref().unwrap().insert_text_entry(&ContentType::PlainText, "from the app", "CB").unwrap();
+    drop(guard);
+    assert_eq!(watcher.notification()["params"]["newest"]["text_content"], "from the app");
+}
+
+#[test]
+fn test_stop() {
+    let (storage, config) = setup("stop");
+    let mut server = Server::start(storage, config.clone()).unwrap();
+    assert!(Server::start(storage, config.clone()).is_err());
+
+    let mut client = Client::authenticated(&config);
+    server.stop();
+    assert!(client.receive().is_none());
+    assert!(!config.socket_path.exists() && !config.token_path.exists());
+
+    // A socket left behind by a crash is replaced
+    let stale = std::os::unix::net::UnixListener::bind(&config.socket_path).unwrap();
+    drop(stale);
+    let restarted = Server::start(storage, config.clone()).unwrap();
+    let mut client = Client::authenticated(&config);
+    assert_eq!(client.call("list", json!({}))["result"]["items"], json!([]));
+    drop(restarted);
+
+    let not_a_socket: PathBuf = config.socket_path.clone();
+    std::fs::write(&not_a_socket, "").unwrap();
+    assert!(Server::start(storage, config).is_err());
+}